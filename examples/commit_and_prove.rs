@@ -0,0 +1,76 @@
+//! Proves `(a + b) * c == d` for private `a`, `b` and `c`, while publishing both `d` and
+//! a Poseidon commitment to the inputs, then verifies the proof.
+//!
+//! Run with `cargo run --example commit_and_prove`.
+
+use halo2_playground::{CircuitInputs, CommittedCircuit};
+use halo2_proofs::{
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
+    poly::{
+        commitment::ParamsProver,
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::{ProverIPA, VerifierIPA},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use halo2curves::{
+    ff::Field,
+    pasta::{EqAffine, Fp},
+};
+use rand_core::OsRng;
+
+fn main() {
+    // A Poseidon hash takes most of the rows, so 2^7 leaves plenty of room.
+    let k = 7;
+
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::random(OsRng),
+        c: Fp::random(OsRng),
+    };
+    let circuit = CommittedCircuit::from(inputs);
+    let public_inputs = circuit.expected_public_inputs();
+    println!("d          = {:?}", public_inputs[0]);
+    println!("commitment = {:?}", public_inputs[1]);
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<EqAffine>, ProverIPA<EqAffine>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&public_inputs[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+    println!("proof size = {} bytes", proof.len());
+
+    let strategy = SingleStrategy::new(&params);
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<
+        IPACommitmentScheme<EqAffine>,
+        VerifierIPA<EqAffine>,
+        Challenge255<EqAffine>,
+        Blake2bRead<&[u8], EqAffine, Challenge255<EqAffine>>,
+        SingleStrategy<EqAffine>,
+    >(
+        &params,
+        pk.get_vk(),
+        strategy,
+        &[&[&public_inputs[..]]],
+        &mut transcript,
+    )
+    .expect("proof verification should not fail");
+
+    println!("success: the proof verifies");
+}
@@ -0,0 +1,166 @@
+//! Circuits assembled at runtime from a list of operations, rather than written out as a
+//! `Circuit` implementation of their own.
+//!
+//! A `CircuitBuilder` collects private inputs and operations on them, each of which is
+//! referred to by the index it was pushed at. Synthesizing the builder interprets the list
+//! with a `FieldChip` configured like the one in `MyCircuit`.
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::pasta::Fp;
+
+use crate::{FieldChip, FieldConfig, MyCircuit, Number, PlaygroundError, RATE, WIDTH};
+
+/// An operation of a `CircuitBuilder`, on the values at the given indices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// `x + y`
+    Add(usize, usize),
+    /// `x - y`
+    Sub(usize, usize),
+    /// `x * y`
+    Mul(usize, usize),
+    /// The Poseidon digest of `x`.
+    Hash(usize),
+}
+
+impl Op {
+    /// Returns the indices of the values the operation reads.
+    fn operands(&self) -> Vec<usize> {
+        match *self {
+            Op::Add(x, y) | Op::Sub(x, y) | Op::Mul(x, y) => vec![x, y],
+            Op::Hash(x) => vec![x],
+        }
+    }
+}
+
+/// A value of a `CircuitBuilder`: either a private input or the result of an operation.
+#[derive(Clone, Debug)]
+enum Step {
+    Input(Value<Fp>),
+    Op(Op),
+}
+
+/// A circuit built up from private inputs and operations on them, exposing the values
+/// marked with `expose` at consecutive instance rows.
+#[derive(Clone, Debug, Default)]
+pub struct CircuitBuilder {
+    steps: Vec<Step>,
+    exposed: Vec<usize>,
+}
+
+impl CircuitBuilder {
+    /// Returns a builder with no inputs or operations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the private input `value` and returns its index.
+    pub fn input(&mut self, value: Value<Fp>) -> usize {
+        self.steps.push(Step::Input(value));
+        self.steps.len() - 1
+    }
+
+    /// Adds `op` and returns the index of its result.
+    ///
+    /// The operands must already be in the builder, which keeps the list in evaluation order.
+    pub fn push(&mut self, op: Op) -> Result<usize, PlaygroundError> {
+        for index in op.operands() {
+            self.check_index(index)?;
+        }
+
+        self.steps.push(Step::Op(op));
+        Ok(self.steps.len() - 1)
+    }
+
+    /// Exposes the value at `index` at the next instance row.
+    pub fn expose(&mut self, index: usize) -> Result<(), PlaygroundError> {
+        self.check_index(index)?;
+        self.exposed.push(index);
+
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), PlaygroundError> {
+        let len = self.steps.len();
+        if index >= len {
+            return Err(PlaygroundError::IndexOutOfBounds { index, len });
+        }
+
+        Ok(())
+    }
+}
+
+impl Circuit<Fp> for CircuitBuilder {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Input(_) => Step::Input(Value::unknown()),
+                Step::Op(op) => Step::Op(*op),
+            })
+            .collect();
+
+        Self {
+            steps,
+            exposed: self.exposed.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let mut values: Vec<Number<Fp>> = Vec::with_capacity(self.steps.len());
+        for (i, step) in self.steps.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("step_{i}"));
+            let value = match *step {
+                Step::Input(value) => chip.load_private(layouter.namespace(|| "input"), value)?,
+                Step::Op(Op::Add(x, y)) => chip.add(
+                    layouter.namespace(|| "add"),
+                    values[x].clone(),
+                    values[y].clone(),
+                )?,
+                Step::Op(Op::Sub(x, y)) => chip.sub(
+                    layouter.namespace(|| "sub"),
+                    values[x].clone(),
+                    values[y].clone(),
+                )?,
+                Step::Op(Op::Mul(x, y)) => chip.mul(
+                    layouter.namespace(|| "mul"),
+                    values[x].clone(),
+                    values[y].clone(),
+                )?,
+                Step::Op(Op::Hash(x)) => {
+                    chip.poseidon_hash(layouter.namespace(|| "hash"), [values[x].clone()])?
+                }
+            };
+            values.push(value);
+        }
+
+        for (row, index) in self.exposed.iter().enumerate() {
+            chip.expose_public(
+                layouter.namespace(|| format!("expose step_{index}")),
+                values[*index].clone(),
+                row,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Plain-data inputs for `MyCircuit`, which can be (de)serialized with the `serde` feature.
+//!
+//! `Value<Fp>` cannot be serialized, so test cases and inputs are stored as plain field
+//! elements and converted into a circuit when needed.
+
+use halo2_proofs::circuit::Value;
+use halo2curves::pasta::Fp;
+
+use crate::MyCircuit;
+
+/// The private inputs `a`, `b` and `c` of `MyCircuit`.
+///
+/// With the `serde` feature, each field element is serialized as the hex encoding of its
+/// little-endian canonical representation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CircuitInputs {
+    #[cfg_attr(feature = "serde", serde(with = "fp_hex"))]
+    pub a: Fp,
+    #[cfg_attr(feature = "serde", serde(with = "fp_hex"))]
+    pub b: Fp,
+    #[cfg_attr(feature = "serde", serde(with = "fp_hex"))]
+    pub c: Fp,
+}
+
+impl From<CircuitInputs> for MyCircuit<Fp> {
+    fn from(inputs: CircuitInputs) -> Self {
+        Self {
+            a: Value::known(inputs.a),
+            b: Value::known(inputs.b),
+            c: Value::known(inputs.c),
+        }
+    }
+}
+
+impl MyCircuit<Fp> {
+    /// Returns the circuit's private inputs, or `None` if any of them is unknown.
+    pub fn inputs(&self) -> Option<CircuitInputs> {
+        let mut inputs = None;
+        self.a.zip(self.b).zip(self.c).map(|((a, b), c)| {
+            inputs = Some(CircuitInputs { a, b, c });
+        });
+
+        inputs
+    }
+}
+
+#[cfg(feature = "serde")]
+mod fp_hex {
+    use halo2curves::{ff::PrimeField, pasta::Fp};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Fp, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = value
+            .to_repr()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Fp, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(D::Error::custom("expected 64 hex digits"));
+        }
+
+        let mut repr = [0u8; 32];
+        for (byte, digits) in repr.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits).map_err(D::Error::custom)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(D::Error::custom)?;
+        }
+
+        Option::from(Fp::from_repr(repr))
+            .ok_or_else(|| D::Error::custom("not a canonical field element"))
+    }
+}
@@ -0,0 +1,61 @@
+//! Rendering of circuit layouts to image files.
+//!
+//! The backend is picked from the extension of the output path: `.svg` files are drawn as
+//! vector graphics, which diff well under version control, and anything else as a bitmap.
+
+use std::{error::Error, path::Path};
+
+use halo2_proofs::{dev::CircuitLayout, plonk::Circuit};
+use halo2curves::pasta::Fp;
+use plotters::{coord::Shift, prelude::*};
+
+/// Size of the rendered layout, in pixels.
+const LAYOUT_SIZE: (u32, u32) = (1024, 768);
+
+/// Renders the layout of `circuit` at size `2^k` to `path`, as an SVG if `path` ends in
+/// `.svg` and as a bitmap in the format of its extension otherwise.
+pub fn render_layout<C: Circuit<Fp>>(
+    circuit: &C,
+    k: u32,
+    title: &str,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let is_svg = path
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("svg"));
+
+    if is_svg {
+        draw(
+            SVGBackend::new(path, LAYOUT_SIZE).into_drawing_area(),
+            circuit,
+            k,
+            title,
+        )
+    } else {
+        draw(
+            BitMapBackend::new(path, LAYOUT_SIZE).into_drawing_area(),
+            circuit,
+            k,
+            title,
+        )
+    }
+}
+
+fn draw<DB, C>(
+    drawing_area: DrawingArea<DB, Shift>,
+    circuit: &C,
+    k: u32,
+    title: &str,
+) -> Result<(), Box<dyn Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    C: Circuit<Fp>,
+{
+    drawing_area.fill(&WHITE)?;
+    let drawing_area = drawing_area.titled(title, ("sans-serif", 60))?;
+    CircuitLayout::default().render(k, circuit, &drawing_area)?;
+    drawing_area.present()?;
+
+    Ok(())
+}
@@ -1,20 +1,39 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeSet, fmt, marker::PhantomData};
 
 use halo2_gadgets::poseidon::{
-    primitives::{Absorbing, ConstantLength, Domain, P128Pow5T3, Spec, Squeezing},
-    PaddedWord, Pow5Chip, Pow5Config, Sponge,
+    primitives::{
+        generate_constants, Absorbing, ConstantLength, Domain, P128Pow5T3, Spec, Squeezing,
+    },
+    Hash as PoseidonHash, PaddedWord, Pow5Chip, Pow5Config, Sponge,
 };
 use halo2_proofs::{
     arithmetic::Field,
     circuit::{AssignedCell, Chip, Layouter, Region, SimpleFloorPlanner, Value},
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+    },
     poly::Rotation,
 };
-use halo2curves::pasta::Fp;
+use halo2curves::{ff::PrimeField, pasta::Fp};
 // TODO import poseidon types
 
+mod circuit_builder;
+mod circuit_inputs;
+mod layout;
+mod round_constants;
 #[cfg(test)]
 mod tests;
+mod transcript_sponge;
+mod verifier;
+
+pub use circuit_builder::{CircuitBuilder, Op};
+pub use circuit_inputs::CircuitInputs;
+pub use layout::render_layout;
+pub use round_constants::{configure_poseidon_with_table, ConstantTable, TableSpec};
+pub use transcript_sponge::{CircuitTranscript, Transcript, TranscriptSponge};
+pub use verifier::{
+    decode_instances, encode_instances, verify_from_bytes, verify_proofs, vk_hash, VerifyStrategy,
+};
 
 // needed for the poseidon config?
 // const T: usize = 3;
@@ -24,8 +43,133 @@ mod tests;
 // const R_P: usize = 57;
 
 /// A variable representing a number.
-#[derive(Clone)]
-struct Number<Fp: Field>(AssignedCell<Fp, Fp>);
+#[derive(Clone, Debug)]
+pub struct Number<Fp: Field>(AssignedCell<Fp, Fp>);
+
+impl<Fp: Field> From<AssignedCell<Fp, Fp>> for Number<Fp> {
+    fn from(cell: AssignedCell<Fp, Fp>) -> Self {
+        Number(cell)
+    }
+}
+
+impl<Fp: Field> From<Number<Fp>> for AssignedCell<Fp, Fp> {
+    fn from(number: Number<Fp>) -> Self {
+        number.0
+    }
+}
+
+impl<Fp: Field> Number<Fp> {
+    /// Compares the values of two numbers, without constraining them to be equal.
+    pub fn value_eq(&self, other: &Self) -> Value<bool> {
+        self.0.value().zip(other.0.value()).map(|(a, b)| a == b)
+    }
+}
+
+/// Errors raised by the `FieldChip` gadgets.
+#[derive(Debug)]
+pub enum PlaygroundError {
+    /// An error raised by the proving system while synthesizing the circuit.
+    Plonk(Error),
+    /// A divisor is known to be zero.
+    DivisionByZero,
+    /// A gadget received no inputs where it needs at least one.
+    EmptyInput,
+    /// Two inputs that must have matching lengths do not.
+    LengthMismatch { expected: usize, actual: usize },
+    /// Inputs that must be pairwise distinct are not.
+    DuplicateInput,
+    /// More bits were requested than fit in a field element.
+    TooManyBits { n_bits: usize, max: usize },
+    /// An index lies outside of the collection it refers to.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// A value is known not to fit in the number of bits it is range-checked to.
+    OutOfRange { n_bits: usize },
+    /// The lower bound of a range lies above its upper bound.
+    EmptyRange,
+    /// A Poseidon parameter set has an odd number of full or partial rounds, which
+    /// `Pow5Chip` cannot process in pairs.
+    OddRounds {
+        full_rounds: usize,
+        partial_rounds: usize,
+    },
+}
+
+impl fmt::Display for PlaygroundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plonk(err) => write!(f, "synthesis error: {err}"),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::EmptyInput => write!(f, "empty input"),
+            Self::LengthMismatch { expected, actual } => {
+                write!(f, "length mismatch: expected {expected}, got {actual}")
+            }
+            Self::DuplicateInput => write!(f, "duplicate input"),
+            Self::TooManyBits { n_bits, max } => {
+                write!(
+                    f,
+                    "{n_bits} bits requested, at most {max} fit in a field element"
+                )
+            }
+            Self::IndexOutOfBounds { index, len } => {
+                write!(f, "index {index} out of bounds for length {len}")
+            }
+            Self::OutOfRange { n_bits } => write!(f, "value does not fit in {n_bits} bits"),
+            Self::EmptyRange => write!(f, "lower bound exceeds upper bound"),
+            Self::OddRounds {
+                full_rounds,
+                partial_rounds,
+            } => write!(
+                f,
+                "{full_rounds} full and {partial_rounds} partial rounds, both must be even"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlaygroundError {}
+
+impl From<Error> for PlaygroundError {
+    fn from(err: Error) -> Self {
+        Self::Plonk(err)
+    }
+}
+
+// Lets gadgets be used with `?` from `Circuit::synthesize`, which must return a
+// `plonk::Error`; domain errors all collapse into `Error::Synthesis`.
+impl From<PlaygroundError> for Error {
+    fn from(err: PlaygroundError) -> Self {
+        match err {
+            PlaygroundError::Plonk(err) => err,
+            _ => Error::Synthesis,
+        }
+    }
+}
+
+/// Returns the `i`-th least significant bit of `a`, read as an integer.
+fn nth_bit(a: &Fp, i: usize) -> bool {
+    (a.to_repr()[i / 8] >> (i % 8)) & 1 == 1
+}
+
+/// Returns whether `a < b`, comparing both as integers.
+fn int_lt(a: &Fp, b: &Fp) -> bool {
+    a.to_repr().iter().rev().lt(b.to_repr().iter().rev())
+}
+
+/// Returns the integer quotient and remainder of `a / b`, by long division over the low
+/// `n_bits` bits of `a`.
+fn divmod_native(a: &Fp, b: &Fp, n_bits: usize) -> (Fp, Fp) {
+    let (mut q, mut r) = (Fp::ZERO, Fp::ZERO);
+    for i in (0..n_bits).rev() {
+        r = r.double() + Fp::from(nth_bit(a, i) as u64);
+        q = q.double();
+        if !int_lt(&r, b) {
+            r -= b;
+            q += Fp::ONE;
+        }
+    }
+
+    (q, r)
+}
 
 // The top-level config that provides all necessary columns and permutations
 // for the other configs.
@@ -41,15 +185,71 @@ pub struct FieldConfig<Fp: Field, const WIDTH: usize, const RATE: usize> {
 
     add_config: AddConfig,
     mul_config: MulConfig,
-    sponge_config: Pow5Config<Fp, WIDTH, RATE>,
-    // TODO add a poseidon config
+    inner_product_config: InnerProductConfig,
+    bool_config: BoolConfig,
+    /// `None` for chips configured by `configure_arithmetic`, which cannot hash.
+    sponge_config: Option<Pow5Config<Fp, WIDTH, RATE>>,
     _marker: PhantomData<Fp>,
 }
 
+/// The number of columns of each kind a `FieldConfig` takes up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnStats {
+    pub advice: usize,
+    pub fixed: usize,
+    pub instance: usize,
+    pub selectors: usize,
+}
+
+impl<Fp: Field, const WIDTH: usize, const RATE: usize> FieldConfig<Fp, WIDTH, RATE> {
+    /// Returns the number of columns of each kind the config takes up.
+    ///
+    /// `Pow5Config` keeps its columns private, so its share is derived from how
+    /// `Pow5Chip::configure` allocates them: one extra advice column for the partial S-box,
+    /// `WIDTH` fixed columns each for the two sets of round constants, and three selectors.
+    ///
+    /// A config without a sponge takes up only its `WIDTH` advice columns, the instance
+    /// column and our own selectors; its constants column belongs to the caller.
+    pub fn column_stats(&self) -> ColumnStats {
+        let selectors = [
+            self.add_config.s_add,
+            self.add_config.s_fibonacci,
+            self.mul_config.s_mul,
+            self.inner_product_config.s_inner_product,
+            self.inner_product_config.s_accumulate,
+            self.bool_config.s_or,
+            self.bool_config.s_bool_inputs,
+        ];
+
+        if self.sponge_config.is_none() {
+            return ColumnStats {
+                advice: self.advice.len(),
+                fixed: 0,
+                instance: 1,
+                selectors: selectors.len(),
+            };
+        }
+
+        ColumnStats {
+            advice: self.advice.len() + 1,
+            fixed: 2 * WIDTH,
+            instance: 1,
+            selectors: selectors.len() + 3,
+        }
+    }
+
+    /// Returns the config of the Poseidon chip, or `Error::Synthesis` if the chip was
+    /// configured by `configure_arithmetic` and so cannot hash.
+    fn sponge_config(&self) -> Result<Pow5Config<Fp, WIDTH, RATE>, Error> {
+        self.sponge_config.clone().ok_or(Error::Synthesis)
+    }
+}
+
 #[derive(Clone, Debug)]
 struct AddConfig {
     advice: [Column<Advice>; 2],
     s_add: Selector,
+    s_fibonacci: Selector,
 }
 
 #[derive(Clone, Debug)]
@@ -58,8 +258,63 @@ struct MulConfig {
     s_mul: Selector,
 }
 
+#[derive(Clone, Debug)]
+struct InnerProductConfig {
+    advice: [Column<Advice>; 2],
+    acc: Column<Advice>,
+    s_inner_product: Selector,
+    s_accumulate: Selector,
+}
+
+#[derive(Clone, Debug)]
+struct BoolConfig {
+    advice: [Column<Advice>; 2],
+    s_or: Selector,
+    s_bool_inputs: Selector,
+}
+
+/// Picks the Poseidon spec `FieldChip` hashes with at a given width.
+pub trait PoseidonSpec<const WIDTH: usize, const RATE: usize> {
+    type Spec: Spec<Fp, WIDTH, RATE>;
+}
+
+impl PoseidonSpec<3, 2> for FieldChip<Fp, 3, 2> {
+    type Spec = P128Pow5T3;
+}
+
+impl PoseidonSpec<5, 4> for FieldChip<Fp, 5, 4> {
+    type Spec = P128Pow5T5;
+}
+
+/// Poseidon with an x^5 S-box over a width-5 state, at the 128-bit security level: 8 full
+/// rounds and 60 partial rounds, with constants generated like those of `P128Pow5T3`.
+#[derive(Debug)]
+pub struct P128Pow5T5;
+
+impl Spec<Fp, 5, 4> for P128Pow5T5 {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        60
+    }
+
+    fn sbox(val: Fp) -> Fp {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> (Vec<[Fp; 5]>, [[Fp; 5]; 5], [[Fp; 5]; 5]) {
+        generate_constants::<_, Self, 5, 4>()
+    }
+}
+
 /// The top-level chip that will implement the `FieldInstructions`.
-struct FieldChip<Fp: Field, const WIDTH: usize, const RATE: usize> {
+pub struct FieldChip<Fp: Field, const WIDTH: usize, const RATE: usize> {
     config: FieldConfig<Fp, WIDTH, RATE>,
     _marker: PhantomData<Fp>,
 }
@@ -74,6 +329,16 @@ struct MulChip<Fp: Field> {
     _marker: PhantomData<Fp>,
 }
 
+struct InnerProductChip<Fp: Field> {
+    config: InnerProductConfig,
+    _marker: PhantomData<Fp>,
+}
+
+struct BoolChip<Fp: Field> {
+    config: BoolConfig,
+    _marker: PhantomData<Fp>,
+}
+
 impl<Fp: Field> Chip<Fp> for AddChip<Fp> {
     type Config = AddConfig;
     type Loaded = ();
@@ -111,12 +376,34 @@ impl<Fp: Field> AddChip<Fp> {
             vec![s_add * (lhs + rhs - out)]
         });
 
-        AddConfig { advice, s_add }
+        let s_fibonacci = meta.selector();
+
+        // Define our recurrence gate, down a single column:
+        //
+        // | a0      | s_fibonacci |
+        // |---------|-------------|
+        // | f_i     | s_fibonacci |
+        // | f_{i+1} |             |
+        // | f_{i+2} |             |
+        meta.create_gate("fibonacci", |meta| {
+            let f_0 = meta.query_advice(advice[0], Rotation::cur());
+            let f_1 = meta.query_advice(advice[0], Rotation::next());
+            let f_2 = meta.query_advice(advice[0], Rotation(2));
+            let s_fibonacci = meta.query_selector(s_fibonacci);
+
+            vec![s_fibonacci * (f_0 + f_1 - f_2)]
+        });
+
+        AddConfig {
+            advice,
+            s_add,
+            s_fibonacci,
+        }
     }
 }
 
 impl<Fp: Field, const WIDTH: usize, const RATE: usize> FieldChip<Fp, WIDTH, RATE> {
-    fn add(
+    pub fn add(
         &self,
         layouter: impl Layouter<Fp>,
         a: Number<Fp>,
@@ -127,6 +414,56 @@ impl<Fp: Field, const WIDTH: usize, const RATE: usize> FieldChip<Fp, WIDTH, RATE
         let add_chip = AddChip::<Fp>::construct(config, ());
         add_chip.add(layouter, a, b)
     }
+
+    pub fn sub(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config().add_config.clone();
+
+        let add_chip = AddChip::<Fp>::construct(config, ());
+        add_chip.sub(layouter, a, b)
+    }
+
+    /// Returns `xs[0] + ... + xs[n - 1]` as a running sum in a single region with one row
+    /// per summand, instead of one two-row `add` region per summand like `sum`.
+    pub fn sum_accumulated(
+        &self,
+        layouter: impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if xs.is_empty() {
+            return Err(PlaygroundError::EmptyInput);
+        }
+
+        let config = self.config().add_config.clone();
+
+        let add_chip = AddChip::<Fp>::construct(config, ());
+        Ok(add_chip.sum(layouter, xs)?)
+    }
+
+    /// Returns the `n`-th term of the sequence `f_{i+2} = f_{i+1} + f_i` starting at `f0`
+    /// and `f1`, with the recurrence gate enabled down a single region of `n + 1` rows.
+    pub fn fibonacci(
+        &self,
+        layouter: impl Layouter<Fp>,
+        f0: Number<Fp>,
+        f1: Number<Fp>,
+        n: usize,
+    ) -> Result<Number<Fp>, Error> {
+        match n {
+            0 => return Ok(f0),
+            1 => return Ok(f1),
+            _ => {}
+        }
+
+        let config = self.config().add_config.clone();
+
+        let add_chip = AddChip::<Fp>::construct(config, ());
+        add_chip.fibonacci(layouter, f0, f1, n)
+    }
 }
 
 impl<Fp: Field> AddChip<Fp> {
@@ -165,6 +502,105 @@ impl<Fp: Field> AddChip<Fp> {
             },
         )
     }
+
+    fn sub(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "sub",
+            |mut region: Region<'_, Fp>| {
+                // Subtraction reuses the addition gate, laid out as `rhs + out = lhs`:
+                //
+                // | a0  | a1  | s_add |
+                // |-----|-----|-------|
+                // | rhs | out | s_add |
+                // | lhs |     |       |
+                config.s_add.enable(&mut region, 0)?;
+
+                b.0.copy_advice(|| "rhs", &mut region, config.advice[0], 0)?;
+                a.0.copy_advice(|| "lhs", &mut region, config.advice[0], 1)?;
+
+                let value = a.0.value().copied() - b.0.value().copied();
+
+                region
+                    .assign_advice(|| "lhs - rhs", config.advice[1], 0, || value)
+                    .map(Number)
+            },
+        )
+    }
+
+    fn sum(&self, mut layouter: impl Layouter<Fp>, xs: &[Number<Fp>]) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "sum",
+            |mut region: Region<'_, Fp>| {
+                // The addition gate is enabled on every row but the last one, turning the
+                // first column into a running sum of the second:
+                //
+                // | a0    | a1  | s_add |
+                // |-------|-----|-------|
+                // | x_0   | x_1 | s_add |
+                // | acc_1 | x_2 | s_add |
+                // | ...   | ... | ...   |
+                // | acc_n |     |       |
+                let mut acc = xs[0]
+                    .0
+                    .copy_advice(|| "x_0", &mut region, config.advice[0], 0)?;
+
+                for (i, x) in xs.iter().enumerate().skip(1) {
+                    config.s_add.enable(&mut region, i - 1)?;
+                    x.0.copy_advice(|| format!("x_{i}"), &mut region, config.advice[1], i - 1)?;
+
+                    let value = acc.value().copied() + x.0.value();
+                    acc = region.assign_advice(
+                        || format!("acc_{i}"),
+                        config.advice[0],
+                        i,
+                        || value,
+                    )?;
+                }
+
+                Ok(Number(acc))
+            },
+        )
+    }
+
+    fn fibonacci(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        f0: Number<Fp>,
+        f1: Number<Fp>,
+        n: usize,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "fibonacci",
+            |mut region: Region<'_, Fp>| {
+                let mut prev =
+                    f0.0.copy_advice(|| "f_0", &mut region, config.advice[0], 0)?;
+                let mut cur =
+                    f1.0.copy_advice(|| "f_1", &mut region, config.advice[0], 1)?;
+
+                for i in 2..=n {
+                    config.s_fibonacci.enable(&mut region, i - 2)?;
+
+                    let value = prev.value().copied() + cur.value();
+                    let next =
+                        region.assign_advice(|| format!("f_{i}"), config.advice[0], i, || value)?;
+                    prev = std::mem::replace(&mut cur, next);
+                }
+
+                Ok(Number(cur))
+            },
+        )
+    }
 }
 
 impl<Fp: Field> Chip<Fp> for MulChip<Fp> {
@@ -229,7 +665,7 @@ impl<Fp: Field> MulChip<Fp> {
 }
 
 impl FieldChip<Fp, WIDTH, RATE> {
-    fn mul(
+    pub fn mul(
         &self,
         layouter: impl Layouter<Fp>,
         a: Number<Fp>,
@@ -239,6 +675,20 @@ impl FieldChip<Fp, WIDTH, RATE> {
         let mul_chip = MulChip::<Fp>::construct(config, ());
         mul_chip.mul(layouter, a, b)
     }
+
+    /// Constrains `a * b = c` for an existing cell `c`, e.g. one loaded from the instance
+    /// column, instead of assigning a new output like `mul`.
+    pub fn assert_mul(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        c: Number<Fp>,
+    ) -> Result<(), Error> {
+        let config = self.config().mul_config.clone();
+        let mul_chip = MulChip::<Fp>::construct(config, ());
+        mul_chip.assert_mul(layouter, a, b, c)
+    }
 }
 
 impl<Fp: Field> MulChip<Fp> {
@@ -277,10 +727,34 @@ impl<Fp: Field> MulChip<Fp> {
             },
         )
     }
+
+    fn assert_mul(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        c: Number<Fp>,
+    ) -> Result<(), Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "assert mul",
+            |mut region: Region<'_, Fp>| {
+                config.s_mul.enable(&mut region, 0)?;
+
+                // Same layout as `mul`, but the output position is a copy of `c`.
+                a.0.copy_advice(|| "lhs", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "rhs", &mut region, config.advice[1], 0)?;
+                c.0.copy_advice(|| "out", &mut region, config.advice[0], 1)?;
+
+                Ok(())
+            },
+        )
+    }
 }
 
-impl<Fp: Field, const WIDTH: usize, const RATE: usize> Chip<Fp> for FieldChip<Fp, WIDTH, RATE> {
-    type Config = FieldConfig<Fp, WIDTH, RATE>;
+impl<Fp: Field> Chip<Fp> for InnerProductChip<Fp> {
+    type Config = InnerProductConfig;
     type Loaded = ();
 
     fn config(&self) -> &Self::Config {
@@ -292,7 +766,7 @@ impl<Fp: Field, const WIDTH: usize, const RATE: usize> Chip<Fp> for FieldChip<Fp
     }
 }
 
-impl FieldChip<Fp, WIDTH, RATE> {
+impl<Fp: Field> InnerProductChip<Fp> {
     fn construct(config: <Self as Chip<Fp>>::Config, _loaded: <Self as Chip<Fp>>::Loaded) -> Self {
         Self {
             config,
@@ -302,89 +776,2750 @@ impl FieldChip<Fp, WIDTH, RATE> {
 
     fn configure(
         meta: &mut ConstraintSystem<Fp>,
-        advice: [Column<Advice>; WIDTH],
-        instance: Column<Instance>,
-        rc_a: [Column<Fixed>; WIDTH],
-        rc_b: [Column<Fixed>; WIDTH],
+        advice: [Column<Advice>; 2],
+        acc: Column<Advice>,
     ) -> <Self as Chip<Fp>>::Config {
-        let add_mul_advice = [advice[0], advice[1]];
-
-        let add_config = AddChip::configure(meta, add_mul_advice);
-        let mul_config = MulChip::configure(meta, add_mul_advice);
-
-        let partial_sbox = meta.advice_column();
-
-        let poseidon_config = Pow5Chip::configure::<P128Pow5T3>(
-            meta,
-            advice.try_into().unwrap(),
-            partial_sbox,
-            rc_a,
-            rc_b,
-        );
+        for column in advice.iter().chain([&acc]) {
+            meta.enable_equality(*column);
+        }
+        let s_inner_product = meta.selector();
+        let s_accumulate = meta.selector();
+
+        meta.create_gate("inner product", |meta| {
+            // | a0  | a1  | s_inner_product |
+            // |-----|-----|-----------------|
+            // | a   | b   | s_inner_product |
+            // | c   | d   |                 |
+            // | out |     |                 |
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let c = meta.query_advice(advice[0], Rotation::next());
+            let d = meta.query_advice(advice[1], Rotation::next());
+            let out = meta.query_advice(advice[0], Rotation(2));
+            let s_inner_product = meta.query_selector(s_inner_product);
+
+            vec![s_inner_product * (a * b + c * d - out)]
+        });
 
-        meta.enable_equality(instance);
+        meta.create_gate("accumulate product", |meta| {
+            // | a0  | a1  | acc     | s_accumulate |
+            // |-----|-----|---------|--------------|
+            // | a_i | b_i | acc_i   | s_accumulate |
+            // |     |     | acc_i+1 |              |
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let acc_cur = meta.query_advice(acc, Rotation::cur());
+            let acc_next = meta.query_advice(acc, Rotation::next());
+            let s_accumulate = meta.query_selector(s_accumulate);
+
+            vec![s_accumulate * (acc_cur + a * b - acc_next)]
+        });
 
-        FieldConfig::<Fp, WIDTH, RATE> {
+        InnerProductConfig {
             advice,
-            instance,
-            add_config,
-            mul_config,
-            sponge_config: poseidon_config,
-            _marker: PhantomData,
+            acc,
+            s_inner_product,
+            s_accumulate,
         }
     }
-}
 
-impl FieldChip<Fp, WIDTH, RATE> {
-    fn load_private(
+    /// Returns `sum_i a_i * b_i` as a running sum in a single region, with one row per
+    /// product plus one for the initial zero.
+    fn inner_product(
         &self,
         mut layouter: impl Layouter<Fp>,
-        value: Value<Fp>,
+        a: &[Number<Fp>],
+        b: &[Number<Fp>],
     ) -> Result<Number<Fp>, Error> {
         let config = self.config();
 
         layouter.assign_region(
-            || "load private",
-            |mut region| {
+            || "inner product",
+            |mut region: Region<'_, Fp>| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc_0", config.acc, 0, Fp::ZERO)?;
+
+                for (i, (a_i, b_i)) in a.iter().zip(b).enumerate() {
+                    config.s_accumulate.enable(&mut region, i)?;
+                    a_i.0
+                        .copy_advice(|| format!("a_{i}"), &mut region, config.advice[0], i)?;
+                    b_i.0
+                        .copy_advice(|| format!("b_{i}"), &mut region, config.advice[1], i)?;
+
+                    let value = acc.value().copied() + a_i.0.value().copied() * b_i.0.value();
+                    acc = region.assign_advice(
+                        || format!("acc_{}", i + 1),
+                        config.acc,
+                        i + 1,
+                        || value,
+                    )?;
+                }
+
+                Ok(Number(acc))
+            },
+        )
+    }
+
+    fn inner_product2(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        [a, b, c, d]: [Number<Fp>; 4],
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "inner product",
+            |mut region: Region<'_, Fp>| {
+                config.s_inner_product.enable(&mut region, 0)?;
+
+                a.0.copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+                c.0.copy_advice(|| "c", &mut region, config.advice[0], 1)?;
+                d.0.copy_advice(|| "d", &mut region, config.advice[1], 1)?;
+
+                let value = a.0.value().copied() * b.0.value() + c.0.value().copied() * d.0.value();
+
                 region
-                    .assign_advice(|| "private input", config.advice[0], 0, || value)
+                    .assign_advice(|| "a * b + c * d", config.advice[0], 2, || value)
                     .map(Number)
             },
         )
     }
+}
 
-    /// Returns `d = (a + b) * c`.
-    fn add_and_mul(
+impl FieldChip<Fp, WIDTH, RATE> {
+    /// Returns `a * b + c * d` with a single three-row gate, instead of two `mul`s and an
+    /// `add` taking two rows each.
+    pub fn inner_product2(
         &self,
-        layouter: &mut impl Layouter<Fp>,
+        layouter: impl Layouter<Fp>,
         a: Number<Fp>,
         b: Number<Fp>,
         c: Number<Fp>,
+        d: Number<Fp>,
     ) -> Result<Number<Fp>, Error> {
-        let ab = self.add(layouter.namespace(|| "a + b"), a, b)?;
-        self.mul(layouter.namespace(|| "(a + b) * c"), ab, c)
+        let config = self.config().inner_product_config.clone();
+        let inner_product_chip = InnerProductChip::<Fp>::construct(config, ());
+        inner_product_chip.inner_product2(layouter, [a, b, c, d])
     }
 
-    // fn get_fiat_shamir_challenge(
-    //     &self,
-    //     layouter: &mut impl Layouter<Fp>,
-    //     input: Number<Fp>,
-    // ) -> Result<Fp, Error> {
-    //     self.squeeze(layouter.namespace(|| "get_fiat_shamir_challenge"), input)
-    // }
-
-    fn expose_public(
+    /// Returns the inner product `a_0 * b_0 + ... + a_{n - 1} * b_{n - 1}` with a single
+    /// accumulating gate, taking `n + 1` rows instead of the `4n - 2` of chaining `mul`s and
+    /// `add`s.
+    pub fn inner_product(
         &self,
-        mut layouter: impl Layouter<Fp>,
-        num: Number<Fp>,
-        row: usize,
-    ) -> Result<(), Error> {
-        let config = self.config();
+        layouter: impl Layouter<Fp>,
+        a: &[Number<Fp>],
+        b: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if a.is_empty() {
+            return Err(PlaygroundError::EmptyInput);
+        }
+        if a.len() != b.len() {
+            return Err(PlaygroundError::LengthMismatch {
+                expected: a.len(),
+                actual: b.len(),
+            });
+        }
 
-        layouter.constrain_instance(num.0.cell(), config.instance, row)
+        let config = self.config().inner_product_config.clone();
+        let inner_product_chip = InnerProductChip::<Fp>::construct(config, ());
+        Ok(inner_product_chip.inner_product(layouter, a, b)?)
     }
 }
 
+impl<Fp: Field> Chip<Fp> for BoolChip<Fp> {
+    type Config = BoolConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<Fp: Field> BoolChip<Fp> {
+    fn construct(config: <Self as Chip<Fp>>::Config, _loaded: <Self as Chip<Fp>>::Loaded) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+    ) -> <Self as Chip<Fp>>::Config {
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let s_or = meta.selector();
+        let s_bool_inputs = meta.selector();
+
+        // | a0  | a1  | s_or | s_bool_inputs |
+        // |-----|-----|------|---------------|
+        // | a   | b   | s_or | s_bool_inputs |
+        // | out |     |      |               |
+        meta.create_gate("or", |meta| {
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let s_or = meta.query_selector(s_or);
+
+            vec![s_or * (a.clone() + b.clone() - a * b - out)]
+        });
+
+        // Optionally enabled alongside the gates above, to also constrain their inputs to
+        // be boolean within the same row.
+        meta.create_gate("boolean inputs", |meta| {
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let s_bool_inputs = meta.query_selector(s_bool_inputs);
+
+            vec![
+                s_bool_inputs.clone() * (a.clone() * a.clone() - a),
+                s_bool_inputs * (b.clone() * b.clone() - b),
+            ]
+        });
+
+        BoolConfig {
+            advice,
+            s_or,
+            s_bool_inputs,
+        }
+    }
+
+    fn or(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        check_inputs: bool,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "or",
+            |mut region: Region<'_, Fp>| {
+                config.s_or.enable(&mut region, 0)?;
+                if check_inputs {
+                    config.s_bool_inputs.enable(&mut region, 0)?;
+                }
+
+                a.0.copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+
+                let value = a.0.value().copied() + b.0.value() - a.0.value().copied() * b.0.value();
+
+                region
+                    .assign_advice(|| "a + b - a * b", config.advice[0], 1, || value)
+                    .map(Number)
+            },
+        )
+    }
+}
+
+impl FieldChip<Fp, WIDTH, RATE> {
+    /// Returns the boolean `a OR b`, as `a + b - a * b`, in a single region.
+    ///
+    /// With `check_inputs`, the same row also constrains `a` and `b` to be boolean;
+    /// otherwise they are assumed to be boolean-constrained already.
+    pub fn or(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        check_inputs: bool,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config().bool_config.clone();
+        let bool_chip = BoolChip::<Fp>::construct(config, ());
+        bool_chip.or(layouter, a, b, check_inputs)
+    }
+}
+
+impl<Fp: Field, const WIDTH: usize, const RATE: usize> Chip<Fp> for FieldChip<Fp, WIDTH, RATE> {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<const WIDTH: usize, const RATE: usize> FieldChip<Fp, WIDTH, RATE>
+where
+    Self: PoseidonSpec<WIDTH, RATE>,
+{
+    pub fn construct(
+        config: <Self as Chip<Fp>>::Config,
+        _loaded: <Self as Chip<Fp>>::Loaded,
+    ) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures the chip over the given columns.
+    ///
+    /// The add and mul gates use the first two `advice` columns, and the accumulating inner
+    /// product gate the third one too; the Poseidon chip uses
+    /// all `WIDTH` of them, plus `rc_a` and `rc_b` for its round constants and one extra
+    /// advice column it allocates for the partial S-box. The column counts are fixed by
+    /// the array types, so only `WIDTH` itself needs checking, which is done at compile
+    /// time next to its definition.
+    ///
+    /// Callers are expected to enable `rc_b[0]` as the constants column, since
+    /// `load_constant` and Poseidon's padding both assign from it.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; WIDTH],
+        instance: Column<Instance>,
+        rc_a: [Column<Fixed>; WIDTH],
+        rc_b: [Column<Fixed>; WIDTH],
+    ) -> <Self as Chip<Fp>>::Config {
+        let config = Self::configure_arithmetic(meta, advice, instance);
+
+        let partial_sbox = meta.advice_column();
+
+        let poseidon_config = Pow5Chip::configure::<<Self as PoseidonSpec<WIDTH, RATE>>::Spec>(
+            meta,
+            advice,
+            partial_sbox,
+            rc_a,
+            rc_b,
+        );
+
+        FieldConfig {
+            sponge_config: Some(poseidon_config),
+            ..config
+        }
+    }
+
+    /// Configures the chip over the given columns without the Poseidon chip, saving its
+    /// extra advice column, round constant columns and selectors where no hashing is
+    /// needed. Any of the hashing methods fails to synthesize on such a chip.
+    ///
+    /// Callers that use `load_constant` still need to enable a constants column.
+    pub fn configure_arithmetic(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; WIDTH],
+        instance: Column<Instance>,
+    ) -> <Self as Chip<Fp>>::Config {
+        let add_mul_advice = [advice[0], advice[1]];
+
+        let add_config = AddChip::configure(meta, add_mul_advice);
+        let mul_config = MulChip::configure(meta, add_mul_advice);
+        let inner_product_config = InnerProductChip::configure(meta, add_mul_advice, advice[2]);
+        let bool_config = BoolChip::configure(meta, add_mul_advice);
+
+        meta.enable_equality(instance);
+
+        FieldConfig::<Fp, WIDTH, RATE> {
+            advice,
+            instance,
+            add_config,
+            mul_config,
+            inner_product_config,
+            bool_config,
+            sponge_config: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocates a fresh set of advice and fixed columns and configures the chip over them,
+    /// sharing only the given `instance` column.
+    ///
+    /// Calling this several times on the same constraint system yields chips over disjoint
+    /// columns, so independent subcircuits can be composed without their regions colliding.
+    pub fn configure_with_fresh_columns(
+        meta: &mut ConstraintSystem<Fp>,
+        instance: Column<Instance>,
+    ) -> <Self as Chip<Fp>>::Config {
+        let advice = [(); WIDTH].map(|_| meta.advice_column());
+        let rc_a = [(); WIDTH].map(|_| meta.fixed_column());
+        let rc_b = [(); WIDTH].map(|_| meta.fixed_column());
+        meta.enable_constant(rc_b[0]);
+
+        Self::configure(meta, advice, instance, rc_a, rc_b)
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load private",
+            |mut region| {
+                region
+                    .assign_advice(|| "private input", config.advice[0], 0, || value)
+                    .map(Number)
+            },
+        )
+    }
+
+    /// Like `load_private`, but labels both the region and the cell with `name`, so that
+    /// layout plots and `MockProver` failures point at the input by name.
+    pub fn load_private_named(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        name: &str,
+        value: Value<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                region
+                    .assign_advice(|| name, config.advice[0], 0, || value)
+                    .map(Number)
+            },
+        )
+    }
+
+    /// Loads the named private `inputs` into a single region, filling the advice columns
+    /// row by row.
+    ///
+    /// Every `load_private` region sits in the first advice column, so loading `n` inputs
+    /// one by one takes `n` rows; packed, they take `n / WIDTH` rounded up.
+    pub fn load_private_packed(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: &[(&str, Value<Fp>)],
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load private packed",
+            |mut region| {
+                inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, value))| {
+                        region
+                            .assign_advice(|| *name, config.advice[i % WIDTH], i / WIDTH, || *value)
+                            .map(Number)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        num: Number<Fp>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let config = self.config();
+
+        layouter.constrain_instance(num.0.cell(), config.instance, row)
+    }
+
+    /// Returns the Poseidon digest of `inputs`.
+    pub fn poseidon_hash<const L: usize>(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: [Number<Fp>; L],
+    ) -> Result<Number<Fp>, Error> {
+        let chip = Pow5Chip::construct(self.config().sponge_config()?);
+        let hasher = PoseidonHash::<
+            _,
+            _,
+            <Self as PoseidonSpec<WIDTH, RATE>>::Spec,
+            ConstantLength<L>,
+            WIDTH,
+            RATE,
+        >::init(chip, layouter.namespace(|| "init hasher"))?;
+
+        hasher
+            .hash(layouter.namespace(|| "hash"), inputs.map(|input| input.0))
+            .map(Number)
+    }
+
+    /// Returns `out_len` field elements squeezed from the Poseidon sponge after absorbing
+    /// `inputs`, for digests wider than one element. The first of them is the digest
+    /// `poseidon_hash` returns.
+    pub fn poseidon_hash_to<const L: usize>(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: [Number<Fp>; L],
+        out_len: usize,
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        let chip = Pow5Chip::construct(self.config().sponge_config()?);
+        let mut sponge = Sponge::<
+            Fp,
+            _,
+            <Self as PoseidonSpec<WIDTH, RATE>>::Spec,
+            Absorbing<PaddedWord<Fp>, RATE>,
+            ConstantLength<L>,
+            WIDTH,
+            RATE,
+        >::new(chip, layouter.namespace(|| "new sponge"))?;
+
+        for (i, value) in inputs
+            .into_iter()
+            .map(|input| PaddedWord::Message(input.0))
+            .chain(<ConstantLength<L> as Domain<Fp, RATE>>::padding(L).map(PaddedWord::Padding))
+            .enumerate()
+        {
+            sponge.absorb(layouter.namespace(|| format!("absorb_{i}")), value)?;
+        }
+
+        let mut sponge = sponge.finish_absorbing(layouter.namespace(|| "finish absorbing"))?;
+        (0..out_len)
+            .map(|i| {
+                sponge
+                    .squeeze(layouter.namespace(|| format!("squeeze_{i}")))
+                    .map(Number)
+            })
+            .collect()
+    }
+}
+
+impl FieldChip<Fp, WIDTH, RATE> {
+    /// Copies the public input at `row` of the instance column into an advice cell, so
+    /// that it can take part in gates.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        row: usize,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load instance",
+            |mut region| {
+                region
+                    .assign_advice_from_instance(
+                        || "public input",
+                        config.instance,
+                        row,
+                        config.advice[0],
+                        0,
+                    )
+                    .map(Number)
+            },
+        )
+    }
+
+    /// Loads a challenge chosen by the verifier, constraining it to the public input at
+    /// `row`, for protocols whose challenges come from an external transcript rather than
+    /// from the in-circuit sponge.
+    ///
+    /// Unlike `load_instance`, the prover supplies the value itself, as taken from its copy
+    /// of the transcript; the copy constraint makes sure it is the one the verifier uses.
+    pub fn load_challenge(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        challenge: Value<Fp>,
+        row: usize,
+    ) -> Result<Number<Fp>, Error> {
+        let challenge = self.load_private(layouter.namespace(|| "load challenge"), challenge)?;
+        self.expose_public(
+            layouter.namespace(|| "bind challenge"),
+            challenge.clone(),
+            row,
+        )?;
+
+        Ok(challenge)
+    }
+
+    /// Loads the private values yielded by `values` into consecutive rows of a single
+    /// region.
+    pub fn load_private_iter(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        values: impl Iterator<Item = Value<Fp>>,
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        let config = self.config();
+
+        // The floor planner may run the region assignment more than once, so the values
+        // are buffered as they are pulled from the iterator on the first pass.
+        let mut values = values.fuse();
+        let mut buffered = Vec::new();
+
+        layouter.assign_region(
+            || "load private iter",
+            |mut region| {
+                buffered.extend(&mut values);
+                buffered
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, value)| {
+                        region
+                            .assign_advice(
+                                || format!("private input_{offset}"),
+                                config.advice[0],
+                                offset,
+                                || *value,
+                            )
+                            .map(Number)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Returns `d = (a + b) * c`.
+    pub fn add_and_mul(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        c: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let ab = self.add(layouter.namespace(|| "a + b"), a, b)?;
+        self.mul(layouter.namespace(|| "(a + b) * c"), ab, c)
+    }
+
+    /// Returns `d = (a + b) * c` together with its Poseidon digest, as `MyCircuit` computes
+    /// them.
+    ///
+    /// The addition and multiplication gates share the advice columns, so they are stacked
+    /// in a single region where the sum feeds the product directly, saving a row and a copy
+    /// constraint over `add_and_mul`:
+    ///
+    /// | a0    | a1 | s_add | s_mul |
+    /// |-------|----|-------|-------|
+    /// | a     | b  | 1     | 0     |
+    /// | a + b | c  | 0     | 1     |
+    /// | d     |    | 0     | 0     |
+    pub fn compute_and_hash(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        c: Number<Fp>,
+    ) -> Result<(Number<Fp>, Number<Fp>), Error> {
+        let config = self.config();
+
+        let d = layouter.assign_region(
+            || "(a + b) * c",
+            |mut region: Region<'_, Fp>| {
+                config.add_config.s_add.enable(&mut region, 0)?;
+                config.mul_config.s_mul.enable(&mut region, 1)?;
+
+                a.0.copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+                let ab = region.assign_advice(
+                    || "a + b",
+                    config.advice[0],
+                    1,
+                    || a.0.value().copied() + b.0.value(),
+                )?;
+                c.0.copy_advice(|| "c", &mut region, config.advice[1], 1)?;
+
+                region
+                    .assign_advice(
+                        || "(a + b) * c",
+                        config.advice[0],
+                        2,
+                        || ab.value().copied() * c.0.value(),
+                    )
+                    .map(Number)
+            },
+        )?;
+        let digest = self.poseidon_hash(layouter.namespace(|| "hash d"), [d.clone()])?;
+
+        Ok((d, digest))
+    }
+
+    // fn get_fiat_shamir_challenge(
+    //     &self,
+    //     layouter: &mut impl Layouter<Fp>,
+    //     input: Number<Fp>,
+    // ) -> Result<Fp, Error> {
+    //     self.squeeze(layouter.namespace(|| "get_fiat_shamir_challenge"), input)
+    // }
+
+    /// Loads a constant into an advice cell, fixed by the constants column.
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        constant: Fp,
+    ) -> Result<Number<Fp>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load constant",
+            |mut region| {
+                region
+                    .assign_advice_from_constant(|| "constant", config.advice[0], 0, constant)
+                    .map(Number)
+            },
+        )
+    }
+
+    /// Constrains `a` to be equal to the constant `constant`.
+    pub fn assert_constant(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        constant: Fp,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "assert constant",
+            |mut region| region.constrain_constant(a.0.cell(), constant),
+        )
+    }
+
+    /// Returns `a * constant`.
+    pub fn mul_by_constant(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        constant: Fp,
+    ) -> Result<Number<Fp>, Error> {
+        let constant = self.load_constant(layouter.namespace(|| "load constant"), constant)?;
+        self.mul(layouter.namespace(|| "a * constant"), a, constant)
+    }
+
+    /// Returns `a + constant`.
+    pub fn add_constant(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        constant: Fp,
+    ) -> Result<Number<Fp>, Error> {
+        let constant = self.load_constant(layouter.namespace(|| "load constant"), constant)?;
+        self.add(layouter.namespace(|| "a + constant"), a, constant)
+    }
+
+    /// Constrains `instance[row] == scale * a + offset`, without exposing `a` itself.
+    pub fn assert_affine_instance(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        scale: Fp,
+        offset: Fp,
+        row: usize,
+    ) -> Result<(), Error> {
+        let scaled = self.mul_by_constant(layouter, a, scale)?;
+        let shifted = self.add_constant(layouter, scaled, offset)?;
+        self.expose_public(layouter.namespace(|| "scale * a + offset"), shifted, row)
+    }
+
+    /// Constrains `a` to be equal to `b`.
+    pub fn assert_equal(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "assert equal",
+            |mut region| region.constrain_equal(a.0.cell(), b.0.cell()),
+        )
+    }
+
+    /// Constrains `a` to be boolean, i.e. `a * a = a`.
+    pub fn assert_bool(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<(), Error> {
+        let a_squared = self.mul(layouter.namespace(|| "a * a"), a.clone(), a.clone())?;
+        self.assert_equal(layouter.namespace(|| "a * a = a"), a_squared, a)
+    }
+
+    /// Returns the boolean `NOT a`, as `1 - a`, constraining `a` to be boolean.
+    pub fn not(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        self.assert_bool(layouter, a.clone())?;
+
+        let one = self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?;
+        self.sub(layouter.namespace(|| "1 - a"), one, a)
+    }
+
+    /// Returns the boolean `a XOR b`, as `a + b - 2 * a * b`.
+    ///
+    /// `a` and `b` are assumed to be boolean-constrained already.
+    pub fn xor(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let sum = self.add(layouter.namespace(|| "a + b"), a.clone(), b.clone())?;
+        let product = self.mul(layouter.namespace(|| "a * b"), a, b)?;
+        let double = self.add(layouter.namespace(|| "2 * a * b"), product.clone(), product)?;
+        self.sub(layouter.namespace(|| "a + b - 2 * a * b"), sum, double)
+    }
+
+    /// Returns `a` if the boolean `cond` is set and `b` otherwise, as `b + cond * (a - b)`.
+    ///
+    /// `cond` is assumed to be boolean-constrained already.
+    pub fn select(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        cond: Number<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let diff = self.sub(layouter.namespace(|| "a - b"), a, b.clone())?;
+        let scaled = self.mul(layouter.namespace(|| "cond * (a - b)"), cond, diff)?;
+        self.add(layouter.namespace(|| "b + cond * (a - b)"), b, scaled)
+    }
+
+    /// Returns `a^2 - b^2`, as `(a + b) * (a - b)`.
+    pub fn diff_of_squares(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let sum = self.add(layouter.namespace(|| "a + b"), a.clone(), b.clone())?;
+        let diff = self.sub(layouter.namespace(|| "a - b"), a, b)?;
+        self.mul(layouter.namespace(|| "(a + b) * (a - b)"), sum, diff)
+    }
+
+    /// Returns the squared distance `(a - b)^2`.
+    pub fn squared_distance(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        let diff = self.sub(layouter.namespace(|| "a - b"), a, b)?;
+        self.mul(layouter.namespace(|| "(a - b)^2"), diff.clone(), diff)
+    }
+
+    /// Returns `sum_i coeffs[i] * values[i]` for coefficients fixed at keygen, as in a
+    /// linear layer with public weights.
+    ///
+    /// The coefficients are fixed through the constants column and combined with `values`
+    /// by the accumulating inner product.
+    pub fn weighted_sum_public(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        values: &[Number<Fp>],
+        coeffs: &[Fp],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if values.len() != coeffs.len() {
+            return Err(PlaygroundError::LengthMismatch {
+                expected: values.len(),
+                actual: coeffs.len(),
+            });
+        }
+
+        let coeffs = coeffs
+            .iter()
+            .enumerate()
+            .map(|(i, coeff)| {
+                self.load_constant(layouter.namespace(|| format!("load coeff_{i}")), *coeff)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner_product(layouter.namespace(|| "weighted sum"), values, &coeffs)
+    }
+
+    /// Constrains the scalar half of a Schnorr signature, `s = r + e * x`, for the nonce `r`,
+    /// challenge `e` and secret key `x`.
+    ///
+    /// The group half, that `r` and `x` are the discrete logarithms of the nonce commitment
+    /// and the public key, is left to the caller.
+    pub fn verify_schnorr_scalar(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        s: Number<Fp>,
+        e: Number<Fp>,
+        x: Number<Fp>,
+        r: Number<Fp>,
+    ) -> Result<(), Error> {
+        let ex = self.mul(layouter.namespace(|| "e * x"), e, x)?;
+        let expected = self.add(layouter.namespace(|| "r + e * x"), r, ex)?;
+        self.assert_equal(layouter.namespace(|| "s = r + e * x"), s, expected)
+    }
+
+    /// Constrains the polynomial with coefficients `coeffs` (constant term first) to
+    /// evaluate to `claimed` at `z`, evaluating it by Horner's rule.
+    ///
+    /// This is plain polynomial evaluation over the coefficients, not a check against an
+    /// opening proof: nothing here involves the commitment, so it only stands in for the
+    /// scalar side of a KZG opening when the circuit knows the whole polynomial.
+    pub fn verify_kzg_opening_scalar(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        coeffs: &[Number<Fp>],
+        z: Number<Fp>,
+        claimed: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let (leading, rest) = coeffs.split_last().ok_or(PlaygroundError::EmptyInput)?;
+
+        let mut eval = leading.clone();
+        for (i, coeff) in rest.iter().enumerate().rev() {
+            let scaled = self.mul(
+                layouter.namespace(|| format!("acc * z ({i})")),
+                eval,
+                z.clone(),
+            )?;
+            eval = self.add(
+                layouter.namespace(|| format!("acc * z + coeff_{i}")),
+                scaled,
+                coeff.clone(),
+            )?;
+        }
+
+        Ok(self.assert_equal(layouter.namespace(|| "p(z) = claimed"), eval, claimed)?)
+    }
+
+    /// Returns the sum of the `values` whose `mask` bit is set, as `sum_i mask[i] * values[i]`,
+    /// constraining each mask bit to be boolean.
+    pub fn masked_sum(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        values: &[Number<Fp>],
+        mask: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        for (i, bit) in mask.iter().enumerate() {
+            self.assert_bool(&mut layouter.namespace(|| format!("mask_{i}")), bit.clone())?;
+        }
+
+        self.inner_product(layouter.namespace(|| "masked sum"), values, mask)
+    }
+
+    /// Returns the random linear combination `sum_i values[i] * challenge^i`, which batches
+    /// several values into one so that they can be checked together.
+    ///
+    /// The powers of `challenge` are built up by successive multiplications and then
+    /// combined with `values` by the accumulating inner product.
+    pub fn random_linear_combination(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        values: &[Number<Fp>],
+        challenge: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if values.is_empty() {
+            return Err(PlaygroundError::EmptyInput);
+        }
+
+        let mut powers = vec![self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?];
+        for i in 1..values.len() {
+            let power = self.mul(
+                layouter.namespace(|| format!("challenge^{i}")),
+                powers[i - 1].clone(),
+                challenge.clone(),
+            )?;
+            powers.push(power);
+        }
+
+        self.inner_product(layouter.namespace(|| "combine"), values, &powers)
+    }
+
+    /// Returns the product of the complex numbers `ar + ai * i` and `br + bi * i`, as its
+    /// real and imaginary parts `(ar * br - ai * bi, ar * bi + ai * br)`.
+    pub fn complex_mul(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        (ar, ai): (Number<Fp>, Number<Fp>),
+        (br, bi): (Number<Fp>, Number<Fp>),
+    ) -> Result<(Number<Fp>, Number<Fp>), Error> {
+        let ar_br = self.mul(layouter.namespace(|| "ar * br"), ar.clone(), br.clone())?;
+        let ai_bi = self.mul(layouter.namespace(|| "ai * bi"), ai.clone(), bi.clone())?;
+        let real = self.sub(layouter.namespace(|| "ar * br - ai * bi"), ar_br, ai_bi)?;
+
+        let imaginary =
+            self.inner_product2(layouter.namespace(|| "ar * bi + ai * br"), ar, bi, ai, br)?;
+
+        Ok((real, imaginary))
+    }
+
+    /// Returns `xs[0] + xs[1] + ... + xs[n - 1]` by chaining `add`s.
+    pub fn sum(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let (first, rest) = xs.split_first().ok_or(PlaygroundError::EmptyInput)?;
+
+        let sum = rest
+            .iter()
+            .enumerate()
+            .try_fold(first.clone(), |acc, (i, x)| {
+                self.add(layouter.namespace(|| format!("sum_{i}")), acc, x.clone())
+            })?;
+
+        Ok(sum)
+    }
+
+    /// Returns the running products `xs[0], xs[0] * xs[1], ..., xs[0] * ... * xs[n - 1]`.
+    pub fn running_product(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let (first, rest) = xs.split_first().ok_or(PlaygroundError::EmptyInput)?;
+
+        let mut products = vec![first.clone()];
+        for (i, x) in rest.iter().enumerate() {
+            let product = self.mul(
+                layouter.namespace(|| format!("product_{i}")),
+                products[i].clone(),
+                x.clone(),
+            )?;
+            products.push(product);
+        }
+
+        Ok(products)
+    }
+
+    /// Returns the fingerprint `prod_i (addr_i + challenge * val_i + challenge^2)` of the
+    /// `(addr, val)` pairs in `tuples`, which memory-consistency arguments compare between
+    /// the reads and writes of a memory.
+    ///
+    /// As for `assert_permutation`, `challenge` must be unpredictable until the tuples are
+    /// fixed.
+    pub fn fingerprint(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        tuples: &[[Number<Fp>; 2]],
+        challenge: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let challenge_squared = self.mul(
+            layouter.namespace(|| "challenge^2"),
+            challenge.clone(),
+            challenge.clone(),
+        )?;
+
+        let terms = tuples
+            .iter()
+            .enumerate()
+            .map(|(i, [addr, val])| {
+                let mut layouter = layouter.namespace(|| format!("tuple_{i}"));
+                let scaled = self.mul(
+                    layouter.namespace(|| "challenge * val"),
+                    challenge.clone(),
+                    val.clone(),
+                )?;
+                let term = self.add(layouter.namespace(|| "addr + ..."), addr.clone(), scaled)?;
+                self.add(
+                    layouter.namespace(|| "... + challenge^2"),
+                    term,
+                    challenge_squared.clone(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let products = self.running_product(layouter, &terms)?;
+        Ok(products[products.len() - 1].clone())
+    }
+
+    /// Constrains `output` to be a permutation of `input`, by the grand product argument
+    /// `prod_i (input[i] + challenge) = prod_i (output[i] + challenge)`.
+    ///
+    /// This is only sound if `challenge` is unpredictable until both vectors are fixed,
+    /// e.g. squeezed from a transcript they were absorbed into.
+    pub fn assert_permutation(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[Number<Fp>],
+        output: &[Number<Fp>],
+        challenge: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        if input.len() != output.len() {
+            return Err(PlaygroundError::LengthMismatch {
+                expected: input.len(),
+                actual: output.len(),
+            });
+        }
+
+        let mut grand_product = |name: &str, xs: &[Number<Fp>]| {
+            let mut layouter = layouter.namespace(|| name);
+            let shifted = xs
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    self.add(
+                        layouter.namespace(|| format!("shift_{i}")),
+                        x.clone(),
+                        challenge.clone(),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let products = self.running_product(&mut layouter, &shifted)?;
+            Ok::<_, PlaygroundError>(products[products.len() - 1].clone())
+        };
+        let input_product = grand_product("input", input)?;
+        let output_product = grand_product("output", output)?;
+
+        self.assert_equal(
+            layouter.namespace(|| "grand products match"),
+            input_product,
+            output_product,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns `a^{-1}`.
+    ///
+    /// The inverse is witnessed and constrained by `a * a^{-1} = 1`, so a zero `a` can
+    /// never satisfy the circuit; we also refuse to synthesize it.
+    pub fn inv_or_error(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        a.0.value()
+            .error_if_known_and(|a| bool::from(a.is_zero()))
+            .map_err(|_| PlaygroundError::DivisionByZero)?;
+
+        let a_inv = self.load_private(
+            layouter.namespace(|| "load a^-1"),
+            a.0.value().map(|a| a.invert().unwrap()),
+        )?;
+        let one = self.mul(layouter.namespace(|| "a * a^-1"), a, a_inv.clone())?;
+        self.assert_constant(layouter.namespace(|| "a * a^-1 = 1"), one, Fp::ONE)?;
+
+        Ok(a_inv)
+    }
+
+    /// Constrains `a` to be nonzero, by showing it has an inverse.
+    pub fn assert_nonzero(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        self.inv_or_error(layouter, a)?;
+
+        Ok(())
+    }
+
+    /// Constrains the elements of `xs` to be pairwise distinct, by asserting every
+    /// difference `x_i - x_j` nonzero.
+    ///
+    /// This takes one `sub` and one nonzero check for each of the `n * (n - 1) / 2` pairs,
+    /// so it only suits small sets.
+    pub fn assert_all_distinct(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        for (i, x_i) in xs.iter().enumerate() {
+            for (j, x_j) in xs.iter().enumerate().skip(i + 1) {
+                let diff = self.sub(
+                    layouter.namespace(|| format!("x_{i} - x_{j}")),
+                    x_i.clone(),
+                    x_j.clone(),
+                )?;
+                self.assert_nonzero(layouter, diff)
+                    .map_err(|err| match err {
+                        PlaygroundError::DivisionByZero => PlaygroundError::DuplicateInput,
+                        err => err,
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `a / b`, refusing to synthesize a zero `b` like `inv_or_error`.
+    pub fn div(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let b_inv = self.inv_or_error(layouter, b)?;
+        Ok(self.mul(layouter.namespace(|| "a * b^-1"), a, b_inv)?)
+    }
+
+    /// Decomposes `a` into `n_bits` little-endian bits, constrained by `assert_bits`.
+    pub fn to_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let max = Fp::NUM_BITS as usize;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+
+        let bits = (0..n_bits)
+            .map(|i| {
+                let bit = a.0.value().map(|a| Fp::from(nth_bit(a, i) as u64));
+                self.load_private(layouter.namespace(|| format!("load bit_{i}")), bit)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assert_bits(layouter, a, &bits)?;
+
+        Ok(bits)
+    }
+
+    /// Constrains the little-endian `bits` to be the decomposition of `a`: each of them
+    /// boolean and all of them recomposing to `a`.
+    ///
+    /// Below the bit size of the field the recomposition cannot wrap around the modulus.
+    /// Over the full bit size it can, as the bits of `a + p` also recompose to `a`, so the
+    /// bits are then also constrained by `assert_below_modulus`.
+    pub fn assert_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        bits: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        let max = Fp::NUM_BITS as usize;
+        if bits.len() > max {
+            return Err(PlaygroundError::TooManyBits {
+                n_bits: bits.len(),
+                max,
+            });
+        }
+
+        for bit in bits {
+            self.assert_bool(layouter, bit.clone())?;
+        }
+        if bits.len() == max {
+            self.assert_below_modulus(layouter, bits)?;
+        }
+
+        let recomposed = self.from_bits(layouter, bits)?;
+        Ok(self.assert_equal(layouter.namespace(|| "bits recompose to a"), recomposed, a)?)
+    }
+
+    /// Constrains the boolean little-endian `bits`, over the full bit size of the field, to
+    /// encode an integer below the modulus `p`.
+    ///
+    /// Walking down from the most significant bit, `eq` tracks whether the bits so far are
+    /// those of `p - 1`. While they are, a set bit where `p - 1` has a clear one would
+    /// exceed it, and a clear bit where `p - 1` has a set one ends the equal prefix.
+    pub fn assert_below_modulus(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bits: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        let n_bits = Fp::NUM_BITS as usize;
+        if bits.len() != n_bits {
+            return Err(PlaygroundError::LengthMismatch {
+                expected: n_bits,
+                actual: bits.len(),
+            });
+        }
+
+        // `None` while the prefix is empty, and so trivially equal.
+        let p_minus_one = -Fp::ONE;
+        let mut eq: Option<Number<Fp>> = None;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let eq_and_bit = match eq.clone() {
+                None => bit.clone(),
+                Some(eq) => self.mul(
+                    layouter.namespace(|| format!("eq_{i} * bit_{i}")),
+                    eq,
+                    bit.clone(),
+                )?,
+            };
+
+            if nth_bit(&p_minus_one, i) {
+                eq = Some(eq_and_bit);
+            } else {
+                self.assert_constant(
+                    layouter.namespace(|| format!("bit_{i} <= p - 1")),
+                    eq_and_bit,
+                    Fp::ZERO,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decomposes `a` into the 32 bytes of its little-endian representation, constrained
+    /// by `assert_bytes`.
+    pub fn to_bytes(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let bytes = (0..32)
+            .map(|i| {
+                let byte = a.0.value().map(|a| Fp::from(a.to_repr()[i] as u64));
+                self.load_private(layouter.namespace(|| format!("load byte_{i}")), byte)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assert_bytes(layouter, a, &bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Constrains the 32 little-endian `bytes` to be the canonical representation of `a`:
+    /// each of them fitting in 8 bits, all of them recomposing to `a`, and the integer they
+    /// encode lying below the modulus, so that the bytes of `a + p` are rejected.
+    ///
+    /// The canonicity check subtracts the bytes from those of `p - 1` with a borrow chain:
+    /// each difference `t_i - byte_i - borrow_i + 256 * borrow_{i + 1}` must fit in a byte,
+    /// with no borrow into the bottom byte nor out of the top one.
+    pub fn assert_bytes(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        bytes: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        if bytes.len() != 32 {
+            return Err(PlaygroundError::LengthMismatch {
+                expected: 32,
+                actual: bytes.len(),
+            });
+        }
+
+        let p_minus_one = (-Fp::ONE).to_repr();
+        let mut borrow: Option<Number<Fp>> = None;
+        for (i, byte) in bytes.iter().enumerate() {
+            self.range_check(layouter, byte.clone(), 8)?;
+
+            let t = self.load_constant(
+                layouter.namespace(|| format!("load t_{i}")),
+                Fp::from(p_minus_one[i] as u64),
+            )?;
+            let mut diff = self.sub(
+                layouter.namespace(|| format!("t_{i} - byte_{i}")),
+                t,
+                byte.clone(),
+            )?;
+            if let Some(borrow) = borrow.take() {
+                diff = self.sub(
+                    layouter.namespace(|| format!("diff_{i} - borrow_{i}")),
+                    diff,
+                    borrow,
+                )?;
+            }
+
+            if i + 1 < bytes.len() {
+                // A negative difference wraps around to a huge field element.
+                let borrow_out = diff
+                    .0
+                    .value()
+                    .map(|diff| Fp::from(int_lt(&Fp::from(255), diff) as u64));
+                let borrow_out = self.load_private(
+                    layouter.namespace(|| format!("load borrow_{}", i + 1)),
+                    borrow_out,
+                )?;
+                self.assert_bool(layouter, borrow_out.clone())?;
+
+                let carry = self.mul_by_constant(layouter, borrow_out.clone(), Fp::from(256))?;
+                diff = self.add(
+                    layouter.namespace(|| format!("diff_{i} + carry")),
+                    diff,
+                    carry,
+                )?;
+                borrow = Some(borrow_out);
+            }
+
+            // Not `range_check`, which would refuse to synthesize non-canonical bytes rather
+            // than leave them to the constraints.
+            self.to_bits(layouter, diff, 8)?;
+        }
+
+        // Horner's rule from the most significant byte down: acc = 256 * acc + byte.
+        let (msb, rest) = bytes.split_last().unwrap();
+        let recomposed =
+            rest.iter()
+                .enumerate()
+                .rev()
+                .try_fold(msb.clone(), |acc, (i, byte)| {
+                    let shifted = self.mul_by_constant(
+                        &mut layouter.namespace(|| format!("shift_{i}")),
+                        acc,
+                        Fp::from(256),
+                    )?;
+                    self.add(
+                        layouter.namespace(|| format!("add byte_{i}")),
+                        shifted,
+                        byte.clone(),
+                    )
+                })?;
+        Ok(self.assert_equal(layouter.namespace(|| "bytes recompose to a"), recomposed, a)?)
+    }
+
+    /// Returns `bits[0] + 2 * bits[1] + ... + 2^{n - 1} * bits[n - 1]`.
+    ///
+    /// The bits themselves are not constrained to be boolean.
+    pub fn from_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bits: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let (msb, rest) = bits.split_last().ok_or(PlaygroundError::EmptyInput)?;
+
+        // Horner's rule from the most significant bit down: acc = 2 * acc + bit.
+        let recomposed = rest
+            .iter()
+            .enumerate()
+            .rev()
+            .try_fold(msb.clone(), |acc, (i, bit)| {
+                let double = self.add(
+                    layouter.namespace(|| format!("double_{i}")),
+                    acc.clone(),
+                    acc,
+                )?;
+                self.add(
+                    layouter.namespace(|| format!("add bit_{i}")),
+                    double,
+                    bit.clone(),
+                )
+            })?;
+
+        Ok(recomposed)
+    }
+
+    /// Returns the bitwise XOR of all of `values`, each of which is constrained to fit in
+    /// `n_bits` bits.
+    pub fn xor_checksum(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        values: &[Number<Fp>],
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let (first, rest) = values.split_first().ok_or(PlaygroundError::EmptyInput)?;
+
+        let mut checksum = self.to_bits(layouter, first.clone(), n_bits)?;
+        for (i, value) in rest.iter().enumerate() {
+            let bits = self.to_bits(layouter, value.clone(), n_bits)?;
+            checksum = checksum
+                .into_iter()
+                .zip(bits)
+                .enumerate()
+                .map(|(j, (acc, bit))| {
+                    self.xor(
+                        &mut layouter.namespace(|| format!("xor value_{i} bit_{j}")),
+                        acc,
+                        bit,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        self.from_bits(layouter, &checksum)
+    }
+
+    /// Returns `a` with the order of its `n_bits` low bits reversed, as used to permute the
+    /// indices of a radix-2 FFT. `a` is constrained to fit in `n_bits` bits.
+    pub fn bit_reverse(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let mut bits = self.to_bits(layouter, a, n_bits)?;
+        bits.reverse();
+        self.from_bits(layouter, &bits)
+    }
+
+    /// Constrains `a` to fit in `n_bits` bits, refusing to synthesize a value known not to.
+    pub fn range_check(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<(), PlaygroundError> {
+        a.0.value()
+            .error_if_known_and(|a| (n_bits..Fp::NUM_BITS as usize).any(|i| nth_bit(a, i)))
+            .map_err(|_| PlaygroundError::OutOfRange { n_bits })?;
+
+        self.to_bits(layouter, a, n_bits)?;
+
+        Ok(())
+    }
+
+    /// Returns `a - b`, range-checked to `n_bits` bits so that an underflowing `b > a`
+    /// cannot wrap around to a huge field element.
+    ///
+    /// `a` and `b` are assumed to fit in `n_bits` bits already.
+    pub fn checked_sub(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let diff = self.sub(layouter.namespace(|| "a - b"), a, b)?;
+        self.range_check(layouter, diff.clone(), n_bits)?;
+
+        Ok(diff)
+    }
+
+    /// Constrains `lo <= a <= hi`, by range-checking both `a - lo` and `hi - a` to `n_bits`
+    /// bits.
+    ///
+    /// `hi - lo` must fit in `n_bits` bits for every value of the range to pass, and
+    /// `n_bits` must stay well below the field size for the two checks to rule out values
+    /// that wrap around.
+    pub fn assert_in_range(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        lo: Fp,
+        hi: Fp,
+        n_bits: usize,
+    ) -> Result<(), PlaygroundError> {
+        if int_lt(&hi, &lo) {
+            return Err(PlaygroundError::EmptyRange);
+        }
+
+        let above_lo = self.add_constant(layouter, a.clone(), -lo)?;
+        self.range_check(layouter, above_lo, n_bits)?;
+
+        let hi = self.load_constant(layouter.namespace(|| "load hi"), hi)?;
+        let below_hi = self.sub(layouter.namespace(|| "hi - a"), hi, a)?;
+        self.range_check(layouter, below_hi, n_bits)
+    }
+
+    /// Returns `a * b`, with `a` and `b` range-checked to `n_bits` bits and their product to
+    /// `2 * n_bits`, so that it is the integer product of the operands.
+    ///
+    /// `n_bits` is limited to half the field size, so that the product cannot wrap around.
+    pub fn mul_bounded(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let max = (Fp::NUM_BITS as usize - 1) / 2;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+
+        self.range_check(layouter, a.clone(), n_bits)?;
+        self.range_check(layouter, b.clone(), n_bits)?;
+        let product = self.mul(layouter.namespace(|| "a * b"), a, b)?;
+        self.range_check(layouter, product.clone(), 2 * n_bits)?;
+
+        Ok(product)
+    }
+
+    /// Returns the integer quotient `q` and remainder `r` of `a / b`, constrained by
+    /// `assert_divmod`.
+    ///
+    /// `n_bits` is limited to half the field size, so that `q * b + r` cannot wrap around
+    /// the field.
+    pub fn divmod(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<(Number<Fp>, Number<Fp>), PlaygroundError> {
+        let max = (Fp::NUM_BITS as usize - 1) / 2;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+        b.0.value()
+            .error_if_known_and(|b| b.is_zero_vartime())
+            .map_err(|_| PlaygroundError::DivisionByZero)?;
+
+        let qr =
+            a.0.value()
+                .zip(b.0.value())
+                .map(|(a, b)| divmod_native(a, b, n_bits));
+        let q = self.load_private(layouter.namespace(|| "load q"), qr.map(|(q, _)| q))?;
+        let r = self.load_private(layouter.namespace(|| "load r"), qr.map(|(_, r)| r))?;
+        self.assert_divmod(layouter, a, b, q.clone(), r.clone(), n_bits)?;
+
+        Ok((q, r))
+    }
+
+    /// Constrains `q` and `r` to be the integer quotient and remainder of `a / b`, by
+    /// `a = q * b + r` with `b`, `q` and `r` fitting in `n_bits` bits and `r < b`.
+    ///
+    /// The range checks keep `q * b + r` below `2^{2 * n_bits}`, so that it cannot wrap
+    /// around the field for `n_bits` up to half the field size. They are plain
+    /// decompositions, so that a forged witness fails the constraints rather than synthesis.
+    pub fn assert_divmod(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        q: Number<Fp>,
+        r: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<(), PlaygroundError> {
+        let max = (Fp::NUM_BITS as usize - 1) / 2;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+
+        self.to_bits(layouter, b.clone(), n_bits)?;
+        self.to_bits(layouter, q.clone(), n_bits)?;
+        self.to_bits(layouter, r.clone(), n_bits)?;
+
+        // `r < b` as `b - r - 1` fitting in `n_bits` bits.
+        let gap = self.sub(layouter.namespace(|| "b - r"), b.clone(), r.clone())?;
+        let gap = self.add_constant(layouter, gap, -Fp::ONE)?;
+        self.to_bits(layouter, gap, n_bits)?;
+
+        let qb = self.mul(layouter.namespace(|| "q * b"), q, b)?;
+        let recomposed = self.add(layouter.namespace(|| "q * b + r"), qb, r)?;
+        Ok(self.assert_equal(layouter.namespace(|| "a = q * b + r"), recomposed, a)?)
+    }
+
+    /// Returns the most significant of the `n_bits` bits of `a`, which is its sign when `a`
+    /// encodes an `n_bits`-wide two's-complement integer.
+    pub fn sign_bit(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let bits = self.to_bits(layouter, a, n_bits)?;
+        bits.last().cloned().ok_or(PlaygroundError::EmptyInput)
+    }
+
+    /// Returns the boolean `a < b`, for `a` and `b` both known to be below `2^n_bits`.
+    ///
+    /// Decomposes `a - b + 2^n_bits` into `n_bits + 1` bits; its top bit is set exactly when
+    /// `a >= b`.
+    pub fn less_than(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let diff = self.sub(layouter.namespace(|| "a - b"), a, b)?;
+        let offset = self.load_constant(
+            layouter.namespace(|| "load 2^n_bits"),
+            Fp::from(2).pow_vartime([n_bits as u64]),
+        )?;
+        let shifted = self.add(layouter.namespace(|| "a - b + 2^n_bits"), diff, offset)?;
+
+        let bits = self.to_bits(layouter, shifted, n_bits + 1)?;
+        let one = self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?;
+        let lt = self.sub(
+            layouter.namespace(|| "1 - (a >= b)"),
+            one,
+            bits[n_bits].clone(),
+        )?;
+
+        Ok(lt)
+    }
+
+    /// Returns the sum of `values`, constraining the running total to stay at or below `max`
+    /// after every step.
+    ///
+    /// Each value is range-checked to `n_bits` bits, so that none can be negative, and
+    /// each running total is compared with `max + 1` by `less_than`. `max` must fit in
+    /// `n_bits` bits.
+    pub fn bounded_accumulate(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        values: &[Number<Fp>],
+        max: Fp,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let max_bits = Fp::NUM_BITS as usize - 2;
+        if n_bits > max_bits {
+            return Err(PlaygroundError::TooManyBits {
+                n_bits,
+                max: max_bits,
+            });
+        }
+        if (n_bits..Fp::NUM_BITS as usize).any(|i| nth_bit(&max, i)) {
+            return Err(PlaygroundError::OutOfRange { n_bits });
+        }
+        let (first, rest) = values.split_first().ok_or(PlaygroundError::EmptyInput)?;
+
+        let bound = self.load_constant(layouter.namespace(|| "load max + 1"), max + Fp::ONE)?;
+        let check = |layouter: &mut _, i: usize, total: Number<Fp>| {
+            let lt = self.less_than(layouter, total, bound.clone(), n_bits)?;
+            self.assert_constant(
+                layouter.namespace(|| format!("total_{i} <= max")),
+                lt,
+                Fp::ONE,
+            )?;
+            Ok::<_, PlaygroundError>(())
+        };
+
+        self.range_check(layouter, first.clone(), n_bits)?;
+        check(layouter, 0, first.clone())?;
+
+        let mut total = first.clone();
+        for (i, value) in rest.iter().enumerate() {
+            self.range_check(layouter, value.clone(), n_bits)?;
+            total = self.add(
+                layouter.namespace(|| format!("total_{}", i + 1)),
+                total,
+                value.clone(),
+            )?;
+            check(layouter, i + 1, total.clone())?;
+        }
+
+        Ok(total)
+    }
+
+    /// Constrains `idx` to be a valid index into an array of length `len`, i.e. to fit in
+    /// `n_bits` bits and to satisfy `idx < len`.
+    ///
+    /// `len` must itself fit in the range, that is not exceed `2^n_bits`.
+    pub fn assert_valid_index(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        idx: Number<Fp>,
+        len: usize,
+        n_bits: usize,
+    ) -> Result<(), PlaygroundError> {
+        if n_bits < usize::BITS as usize && len > 1 << n_bits {
+            return Err(PlaygroundError::OutOfRange { n_bits });
+        }
+
+        self.range_check(layouter, idx.clone(), n_bits)?;
+        let len = self.load_constant(layouter.namespace(|| "load len"), Fp::from(len as u64))?;
+        let lt = self.less_than(layouter, idx, len, n_bits)?;
+        self.assert_constant(layouter.namespace(|| "idx < len"), lt, Fp::ONE)?;
+
+        Ok(())
+    }
+
+    /// Returns the one-hot encoding of `idx` over `len` positions, constraining `idx` to be a
+    /// valid index.
+    ///
+    /// The selector of each position is the product of each bit of `idx` or its negation.
+    pub fn one_hot(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        idx: Number<Fp>,
+        len: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        if len == 0 {
+            return Err(PlaygroundError::EmptyInput);
+        }
+
+        let n_bits = (usize::BITS - (len - 1).leading_zeros()).max(1) as usize;
+        self.assert_valid_index(layouter, idx.clone(), len, n_bits)?;
+
+        let bits = self.to_bits(layouter, idx, n_bits)?;
+        let not_bits = bits
+            .iter()
+            .map(|bit| self.not(layouter, bit.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let selectors = (0..len)
+            .map(|j| {
+                let factors = (0..n_bits).map(|i| {
+                    if j >> i & 1 == 1 {
+                        bits[i].clone()
+                    } else {
+                        not_bits[i].clone()
+                    }
+                });
+                factors
+                    .enumerate()
+                    .try_fold(None, |acc, (i, factor)| match acc {
+                        None => Ok::<_, Error>(Some(factor)),
+                        Some(acc) => self
+                            .mul(
+                                layouter.namespace(|| format!("selector_{j} factor_{i}")),
+                                acc,
+                                factor,
+                            )
+                            .map(Some),
+                    })
+                    .map(|selector| selector.expect("n_bits is at least one"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(selectors)
+    }
+
+    /// Returns `arr[idx]`, constraining `idx` to be a valid index into `arr`.
+    ///
+    /// The one-hot encoding of `idx` picks out the element as a masked sum.
+    pub fn select_index(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        arr: &[Number<Fp>],
+        idx: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let selectors = self.one_hot(layouter, idx, arr.len())?;
+        self.inner_product(layouter.namespace(|| "select arr[idx]"), arr, &selectors)
+    }
+
+    /// Returns a copy of `arr` with `arr[idx]` replaced by `value`, constraining `idx` to be a
+    /// valid index into `arr`.
+    ///
+    /// Each element becomes `arr[j] + selector_j * (value - arr[j])` for the one-hot encoding
+    /// of `idx`.
+    pub fn array_set(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        arr: &[Number<Fp>],
+        idx: Number<Fp>,
+        value: Number<Fp>,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let selectors = self.one_hot(layouter, idx, arr.len())?;
+
+        let updated = arr
+            .iter()
+            .zip(selectors)
+            .enumerate()
+            .map(|(j, (x, selector))| {
+                let mut layouter = layouter.namespace(|| format!("set arr_{j}"));
+                let delta =
+                    self.sub(layouter.namespace(|| "value - x"), value.clone(), x.clone())?;
+                let delta = self.mul(layouter.namespace(|| "selector * delta"), selector, delta)?;
+                self.add(layouter.namespace(|| "x + delta"), x.clone(), delta)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(updated)
+    }
+
+    /// Returns `a XOR mask` over `n_bits` bits, decomposing `a` and recomposing the result.
+    ///
+    /// With a constant mask each bit either passes through or is negated as `1 - bit`, so
+    /// no multiplication is needed. The mask must fit in `n_bits` bits.
+    pub fn xor_const(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        mask: Fp,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if (n_bits..Fp::NUM_BITS as usize).any(|i| nth_bit(&mask, i)) {
+            return Err(PlaygroundError::OutOfRange { n_bits });
+        }
+
+        let bits = self.to_bits(layouter, a, n_bits)?;
+        let one = self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?;
+        let bits = bits
+            .into_iter()
+            .enumerate()
+            .map(|(i, bit)| {
+                if nth_bit(&mask, i) {
+                    self.sub(
+                        layouter.namespace(|| format!("1 - bit_{i}")),
+                        one.clone(),
+                        bit,
+                    )
+                } else {
+                    Ok(bit)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.from_bits(layouter, &bits)
+    }
+
+    /// Returns the number of set bits among the `n_bits` low bits of `a`, which must fit in
+    /// them.
+    pub fn popcount(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let bits = self.to_bits(layouter, a, n_bits)?;
+        self.sum(layouter, &bits)
+    }
+
+    /// Constrains at least `k` of the boolean `bits` to be set.
+    ///
+    /// The bits are assumed to be boolean-constrained already.
+    pub fn assert_popcount_ge(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bits: &[Number<Fp>],
+        k: usize,
+    ) -> Result<(), PlaygroundError> {
+        let popcount = self.sum(layouter, bits)?;
+        let k_cell = self.load_constant(layouter.namespace(|| "load k"), Fp::from(k as u64))?;
+
+        // Both the popcount and `k` must fit in `n_bits` for the comparison to be sound.
+        let n_bits = (usize::BITS - bits.len().max(k).leading_zeros()) as usize;
+        let lt = self.less_than(layouter, popcount, k_cell, n_bits)?;
+        self.assert_constant(layouter.namespace(|| "popcount >= k"), lt, Fp::ZERO)?;
+
+        Ok(())
+    }
+
+    /// Returns the Poseidon digest of each of `pairs`.
+    ///
+    /// This is only a convenience over calling `poseidon_hash` on each pair: every digest
+    /// still gets its own chip, hasher and permutation regions, so it saves no rows.
+    pub fn poseidon_hash_batch(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        pairs: &[[Number<Fp>; 2]],
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(i, pair)| {
+                Ok(self.poseidon_hash(layouter.namespace(|| format!("hash_{i}")), pair.clone())?)
+            })
+            .collect()
+    }
+
+    /// Hashes `inputs` with Poseidon and returns the `n_bits` least significant bits of the
+    /// digest, little-endian, as boolean cells.
+    pub fn hash_to_bits<const L: usize>(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        inputs: [Number<Fp>; L],
+        n_bits: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let max = Fp::NUM_BITS as usize;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+
+        let digest = self.poseidon_hash(layouter.namespace(|| "hash inputs"), inputs)?;
+        self.low_bits(layouter, digest, n_bits)
+    }
+
+    /// Squeezes a challenge from `transcript` and returns its `n_bits` least significant
+    /// bits, little-endian, as boolean cells.
+    pub fn squeeze_challenge_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        transcript: &mut CircuitTranscript,
+        n_bits: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let max = Fp::NUM_BITS as usize;
+        if n_bits > max {
+            return Err(PlaygroundError::TooManyBits { n_bits, max });
+        }
+
+        let challenge = transcript.squeeze(self, layouter.namespace(|| "squeeze challenge"))?;
+        self.low_bits(layouter, challenge, n_bits)
+    }
+
+    /// Returns the `n_bits` least significant bits of `a`, little-endian, as boolean cells.
+    ///
+    /// `a` is decomposed over the full bit size of the field, and the decomposition is
+    /// constrained to be canonical, so the bits of `a + p` cannot stand in for those of `a`.
+    pub fn low_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        let mut bits = self.to_bits(layouter, a, Fp::NUM_BITS as usize)?;
+        bits.truncate(n_bits);
+
+        Ok(bits)
+    }
+
+    /// Evaluates at `x` the polynomial interpolating `points`, using the Lagrange formula
+    /// `sum_i y_i * prod_{j != i} (x - x_j) / (x_i - x_j)`.
+    ///
+    /// Duplicate x-coordinates are rejected at synthesis time, and can never satisfy the
+    /// circuit since they make a denominator zero.
+    pub fn lagrange_eval(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        points: &[(Number<Fp>, Number<Fp>)],
+        x: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        for (i, (x_i, _)) in points.iter().enumerate() {
+            for (x_j, _) in &points[i + 1..] {
+                x_i.value_eq(x_j)
+                    .error_if_known_and(|eq| *eq)
+                    .map_err(|_| PlaygroundError::DuplicateInput)?;
+            }
+        }
+
+        let terms = points
+            .iter()
+            .enumerate()
+            .map(|(i, (x_i, y_i))| {
+                let mut num = y_i.clone();
+                let mut den: Option<Number<Fp>> = None;
+                for (j, (x_j, _)) in points.iter().enumerate().filter(|(j, _)| *j != i) {
+                    let x_minus_x_j = self.sub(
+                        layouter.namespace(|| format!("x - x_{j}")),
+                        x.clone(),
+                        x_j.clone(),
+                    )?;
+                    num = self.mul(
+                        layouter.namespace(|| format!("num_{i} * (x - x_{j})")),
+                        num,
+                        x_minus_x_j,
+                    )?;
+
+                    let x_i_minus_x_j = self.sub(
+                        layouter.namespace(|| format!("x_{i} - x_{j}")),
+                        x_i.clone(),
+                        x_j.clone(),
+                    )?;
+                    den = Some(match den {
+                        Some(den) => self.mul(
+                            layouter.namespace(|| format!("den_{i} * (x_{i} - x_{j})")),
+                            den,
+                            x_i_minus_x_j,
+                        )?,
+                        None => x_i_minus_x_j,
+                    });
+                }
+
+                match den {
+                    Some(den) => self.div(layouter, num, den),
+                    // A single point interpolates the constant polynomial `y_0`.
+                    None => Ok(num),
+                }
+            })
+            .collect::<Result<Vec<_>, PlaygroundError>>()?;
+
+        self.sum(layouter, &terms)
+    }
+
+    /// Exposes the nullifier `Poseidon(secret, index)` at `row` of the instance column, so
+    /// that spending the same note twice can be detected, and returns it.
+    pub fn derive_nullifier(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        secret: Number<Fp>,
+        index: Number<Fp>,
+        row: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let nullifier = self.poseidon_hash(layouter.namespace(|| "nullifier"), [secret, index])?;
+        self.expose_public(
+            layouter.namespace(|| "expose nullifier"),
+            nullifier.clone(),
+            row,
+        )?;
+
+        Ok(nullifier)
+    }
+
+    /// Returns the pseudo-random function output `Poseidon(PRF_TAG, key, input)`.
+    ///
+    /// The tag makes the hash three inputs long, which alone already separates it from the
+    /// two-input hashes used elsewhere; the tag keeps it separate from any other
+    /// three-input hash too.
+    pub fn prf(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: Number<Fp>,
+        input: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let tag = self.load_constant(layouter.namespace(|| "load prf tag"), Fp::from(PRF_TAG))?;
+        Ok(self.poseidon_hash(layouter.namespace(|| "prf"), [tag, key, input])?)
+    }
+
+    /// Encrypts `plaintext` under `key` with a Poseidon duplex sponge.
+    ///
+    /// The sponge absorbs the key, then for each element squeezes a keystream element,
+    /// adds it to the plaintext and absorbs the resulting ciphertext, so that every
+    /// keystream element depends on all the ciphertext before it. There is no nonce, so a
+    /// key must only ever encrypt a single message.
+    pub fn poseidon_encrypt(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[Number<Fp>],
+        key: Number<Fp>,
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        self.poseidon_duplex(layouter, plaintext, key, true)
+    }
+
+    /// Decrypts `ciphertext` produced by `poseidon_encrypt` under `key`.
+    pub fn poseidon_decrypt(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: &[Number<Fp>],
+        key: Number<Fp>,
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        self.poseidon_duplex(layouter, ciphertext, key, false)
+    }
+
+    /// Returns the next challenge of a ratchet, squeezed from the running `transcript` after
+    /// absorbing the previous challenge `prev`.
+    ///
+    /// Every step of a chain goes through the same sponge, so each challenge depends on the
+    /// whole history of the transcript rather than on `prev` alone.
+    pub fn ratchet_challenge(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        transcript: &mut Transcript,
+        prev: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        transcript.append_scalar(layouter.namespace(|| "absorb prev"), prev)?;
+        transcript.challenge(layouter.namespace(|| "squeeze next"))
+    }
+
+    /// Runs the duplex sponge shared by `poseidon_encrypt` and `poseidon_decrypt` over
+    /// `input`, which is the plaintext when `encrypt` is set and the ciphertext otherwise.
+    pub fn poseidon_duplex(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[Number<Fp>],
+        key: Number<Fp>,
+        encrypt: bool,
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        let mut transcript = Transcript::new(self, layouter.namespace(|| "init sponge"))?;
+        transcript.append_scalar(layouter.namespace(|| "absorb key"), key)?;
+
+        input
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let mut layouter = layouter.namespace(|| format!("element_{i}"));
+                let keystream = transcript.challenge(layouter.namespace(|| "keystream"))?;
+                let (output, ciphertext) = if encrypt {
+                    let c = self.add(layouter.namespace(|| "p + k"), x.clone(), keystream)?;
+                    (c.clone(), c)
+                } else {
+                    let p = self.sub(layouter.namespace(|| "c - k"), x.clone(), keystream)?;
+                    (p, x.clone())
+                };
+                transcript.append_scalar(layouter.namespace(|| "absorb ciphertext"), ciphertext)?;
+
+                Ok(output)
+            })
+            .collect()
+    }
+
+    /// Returns the digest of `arr` squeezed from a transcript sponge that absorbs the length
+    /// of `arr` before its elements.
+    ///
+    /// Unlike `poseidon_hash`, the length is only known at synthesis. The transcript sponge
+    /// does not pad its inputs, so without the length prefix `[1]` and `[1, 0]` would
+    /// leave the sponge in the same state and collide.
+    pub fn hash_array(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        arr: &[Number<Fp>],
+    ) -> Result<Number<Fp>, Error> {
+        let len = self.load_constant(
+            layouter.namespace(|| "load length"),
+            Fp::from(arr.len() as u64),
+        )?;
+
+        let mut transcript = Transcript::new(self, layouter.namespace(|| "init sponge"))?;
+        transcript.append_scalar(layouter.namespace(|| "absorb length"), len)?;
+        transcript.append_scalars(layouter, arr)?;
+        transcript.challenge(layouter.namespace(|| "squeeze digest"))
+    }
+
+    /// Returns the `hash_array` digest of the vector of length `len` that holds the given
+    /// `(index, value)` entries and zero everywhere else.
+    ///
+    /// All the zeros are copies of a single constant cell. Indices are checked to be
+    /// distinct and in bounds at synthesis, since they fix the shape of the circuit.
+    pub fn hash_sparse(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        entries: &[(usize, Number<Fp>)],
+        len: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let mut dense = vec![None; len];
+        for (index, value) in entries {
+            let slot = dense
+                .get_mut(*index)
+                .ok_or(PlaygroundError::IndexOutOfBounds { index: *index, len })?;
+            if slot.replace(value.clone()).is_some() {
+                return Err(PlaygroundError::DuplicateInput);
+            }
+        }
+
+        let zero = self.load_constant(layouter.namespace(|| "load zero"), Fp::ZERO)?;
+        let dense = dense
+            .into_iter()
+            .map(|value| value.unwrap_or_else(|| zero.clone()))
+            .collect::<Vec<_>>();
+
+        Ok(self.hash_array(layouter, &dense)?)
+    }
+
+    /// Constrains `digest_short` to be the `hash_array` digest of `prefix` and `digest_long`
+    /// that of `prefix ++ suffix`, as when a commitment is extended with more data.
+    pub fn assert_prefix_consistent(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        digest_short: Number<Fp>,
+        digest_long: Number<Fp>,
+        prefix: &[Number<Fp>],
+        suffix: &[Number<Fp>],
+    ) -> Result<(), Error> {
+        let short = self.hash_array(&mut layouter.namespace(|| "hash prefix"), prefix)?;
+        self.assert_equal(
+            layouter.namespace(|| "digest_short = H(prefix)"),
+            short,
+            digest_short,
+        )?;
+
+        let data = [prefix, suffix].concat();
+        let long = self.hash_array(&mut layouter.namespace(|| "hash prefix ++ suffix"), &data)?;
+        self.assert_equal(
+            layouter.namespace(|| "digest_long = H(prefix ++ suffix)"),
+            long,
+            digest_long,
+        )
+    }
+
+    /// Returns `Poseidon(a)` if the boolean `cond` is set and `Poseidon(b)` otherwise,
+    /// constraining `cond` to be boolean.
+    ///
+    /// The input is selected before hashing, so only one hash is synthesized.
+    pub fn conditional_hash(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        cond: Number<Fp>,
+        a: Number<Fp>,
+        b: Number<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        self.assert_bool(layouter, cond.clone())?;
+        let input = self.select(layouter, cond, a, b)?;
+        self.poseidon_hash(layouter.namespace(|| "hash selected"), [input])
+    }
+
+    /// Exposes the digest `Poseidon(value)` at `row` of the instance column, committing to
+    /// `value` without revealing it.
+    pub fn self_commit(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        value: Number<Fp>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let digest = self.poseidon_hash(layouter.namespace(|| "hash value"), [value])?;
+        self.expose_public(layouter.namespace(|| "expose commitment"), digest, row)
+    }
+
+    /// Returns the address of the public key `(pubkey_x, pubkey_y)`: the low `ADDRESS_BITS`
+    /// bits of `Poseidon(pubkey_x, pubkey_y)`.
+    pub fn derive_address(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        pubkey_x: Number<Fp>,
+        pubkey_y: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let bits = self.hash_to_bits(layouter, [pubkey_x, pubkey_y], ADDRESS_BITS)?;
+        self.from_bits(layouter, &bits)
+    }
+
+    /// Constrains the Poseidon digest of the whole `witness` to equal the public input at
+    /// `commitment_row`, binding the statement to this exact witness.
+    ///
+    /// The witness length is a const generic, since the digest is a constant-length hash.
+    pub fn bind_witness<const L: usize>(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        witness: [Number<Fp>; L],
+        commitment_row: usize,
+    ) -> Result<(), PlaygroundError> {
+        let digest = self.poseidon_hash(layouter.namespace(|| "hash witness"), witness)?;
+        self.expose_public(
+            layouter.namespace(|| "bind witness"),
+            digest,
+            commitment_row,
+        )?;
+
+        Ok(())
+    }
+
+    /// Commits to `xs` with a Poseidon Merkle tree and returns its root.
+    ///
+    /// The leaves are padded with zeros up to the next power of two.
+    pub fn vector_commitment(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if xs.is_empty() {
+            return Err(PlaygroundError::EmptyInput);
+        }
+
+        let mut level = xs.to_vec();
+        for i in xs.len()..xs.len().next_power_of_two() {
+            let zero = self.load_constant(layouter.namespace(|| format!("pad_{i}")), Fp::ZERO)?;
+            level.push(zero);
+        }
+
+        let mut depth = 0;
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .enumerate()
+                .map(|(i, pair)| {
+                    self.poseidon_hash(
+                        layouter.namespace(|| format!("node_{depth}_{i}")),
+                        [pair[0].clone(), pair[1].clone()],
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            depth += 1;
+        }
+
+        Ok(level.remove(0))
+    }
+
+    /// Constrains `leaf` to sit at position `index` of the vector committed to by `root`,
+    /// given the sibling of each node on its path from the bottom of the tree up.
+    pub fn open_commitment(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        leaf: Number<Fp>,
+        index: usize,
+        siblings: &[Number<Fp>],
+        root: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let len = 1 << siblings.len();
+        if index >= len {
+            return Err(PlaygroundError::IndexOutOfBounds { index, len });
+        }
+
+        let node = siblings
+            .iter()
+            .enumerate()
+            .try_fold(leaf, |node, (depth, sibling)| {
+                let pair = if (index >> depth) & 1 == 0 {
+                    [node, sibling.clone()]
+                } else {
+                    [sibling.clone(), node]
+                };
+                self.poseidon_hash(layouter.namespace(|| format!("path_{depth}")), pair)
+            })?;
+        self.assert_equal(layouter.namespace(|| "path reaches root"), node, root)?;
+
+        Ok(())
+    }
+
+    /// Constrains `leaf` to be a leaf of the Merkle tree with root `root` and returns its
+    /// nullifier `Poseidon(secret, leaf)`, as when spending a note from a privacy pool.
+    ///
+    /// Each step of `path` holds the sibling of the current node together with a bit that is
+    /// set when the node is the right child. Unlike in `open_commitment`, the position of the
+    /// leaf is witnessed rather than fixed, so the circuit does not depend on it.
+    pub fn verify_membership_and_nullify(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        leaf: Number<Fp>,
+        path: &[(Number<Fp>, Number<Fp>)],
+        root: Number<Fp>,
+        secret: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        for (depth, (_, is_right)) in path.iter().enumerate() {
+            self.assert_bool(
+                &mut layouter.namespace(|| format!("is_right_{depth}")),
+                is_right.clone(),
+            )?;
+        }
+
+        let node = self.root_from_path(layouter, leaf.clone(), path)?;
+        self.assert_equal(layouter.namespace(|| "path reaches root"), node, root)?;
+
+        self.derive_nullifier(layouter.namespace(|| "derive nullifier"), secret, leaf)
+    }
+
+    /// Returns the root reached by hashing `leaf` up the Merkle `path`, each step of which
+    /// holds the sibling of the current node and a bit set when the node is the right child.
+    ///
+    /// The bits are assumed to be boolean-constrained already.
+    pub fn root_from_path(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        leaf: Number<Fp>,
+        path: &[(Number<Fp>, Number<Fp>)],
+    ) -> Result<Number<Fp>, Error> {
+        let mut node = leaf;
+        for (depth, (sibling, is_right)) in path.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("path_{depth}"));
+
+            let left = self.select(
+                &mut layouter,
+                is_right.clone(),
+                sibling.clone(),
+                node.clone(),
+            )?;
+            let right = self.select(&mut layouter, is_right.clone(), node, sibling.clone())?;
+            node = self.poseidon_hash(layouter.namespace(|| "hash"), [left, right])?;
+        }
+
+        Ok(node)
+    }
+
+    /// Constrains `Poseidon(key, value)` to be a leaf of the sparse Merkle tree with root
+    /// `root`, at the position given by the key.
+    ///
+    /// The tree has one level per sibling, and the key is decomposed into as many bits:
+    /// bit `i`, counted from the least significant, is set when the node at height `i` is
+    /// the right child. Keys that do not fit in the tree are rejected.
+    pub fn verify_smt_inclusion(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: Number<Fp>,
+        value: Number<Fp>,
+        siblings: &[Number<Fp>],
+        root: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let leaf = self.poseidon_hash(layouter.namespace(|| "hash leaf"), [key.clone(), value])?;
+        self.assert_smt_leaf(layouter, key, leaf, siblings, root)
+    }
+
+    /// Constrains `key` to be absent from the sparse Merkle tree with root `root`, that is
+    /// its leaf to be the empty leaf `SMT_EMPTY_LEAF`, as laid out by `verify_smt_inclusion`.
+    pub fn verify_smt_exclusion(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: Number<Fp>,
+        siblings: &[Number<Fp>],
+        root: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let leaf = self.load_constant(layouter.namespace(|| "load empty leaf"), SMT_EMPTY_LEAF)?;
+        self.assert_smt_leaf(layouter, key, leaf, siblings, root)
+    }
+
+    /// Constrains `leaf` to sit at the position given by `key` in the sparse Merkle tree
+    /// with root `root`.
+    pub fn assert_smt_leaf(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: Number<Fp>,
+        leaf: Number<Fp>,
+        siblings: &[Number<Fp>],
+        root: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let bits = self.to_bits(layouter, key, siblings.len())?;
+
+        let path = siblings.iter().cloned().zip(bits).collect::<Vec<_>>();
+        let node = self.root_from_path(layouter, leaf, &path)?;
+        Ok(self.assert_equal(layouter.namespace(|| "path reaches root"), node, root)?)
+    }
+
+    /// Constrains `digest` to be the Poseidon digest of one of `candidates`, without
+    /// revealing which: the product of `digest - Poseidon(candidate)` over all candidates
+    /// must vanish.
+    pub fn assert_hash_in_set(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        digest: Number<Fp>,
+        candidates: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        let differences = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                let hash = self.poseidon_hash(
+                    layouter.namespace(|| format!("hash candidate_{i}")),
+                    [candidate.clone()],
+                )?;
+                self.sub(
+                    layouter.namespace(|| format!("digest - hash_{i}")),
+                    digest.clone(),
+                    hash,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (first, rest) = differences
+            .split_first()
+            .ok_or(PlaygroundError::EmptyInput)?;
+        let product =
+            rest.iter()
+                .enumerate()
+                .try_fold(first.clone(), |product, (i, difference)| {
+                    self.mul(
+                        layouter.namespace(|| format!("product_{i}")),
+                        product,
+                        difference.clone(),
+                    )
+                })?;
+        self.assert_constant(layouter.namespace(|| "product = 0"), product, Fp::ZERO)?;
+
+        Ok(())
+    }
+
+    /// Constrains the state transition `new_state = Poseidon(old_state, input)`, as in a
+    /// rollup where both states are public and the input is private.
+    pub fn verify_transition(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        old_state: Number<Fp>,
+        input: Number<Fp>,
+        new_state: Number<Fp>,
+    ) -> Result<(), Error> {
+        let next = self.poseidon_hash(layouter.namespace(|| "transition"), [old_state, input])?;
+        self.assert_equal(layouter.namespace(|| "reaches new state"), next, new_state)
+    }
+
+    /// Returns the end of the hash chain `h = Poseidon(h, item)` folded over `items`,
+    /// starting from `h = start`.
+    pub fn hash_chain(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        start: Number<Fp>,
+        items: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let end = items.iter().enumerate().try_fold(start, |h, (i, item)| {
+            self.poseidon_hash(
+                layouter.namespace(|| format!("link_{i}")),
+                [h, item.clone()],
+            )
+        })?;
+
+        Ok(end)
+    }
+
+    /// Constrains the hash chain over `items` from `start` to end at `expected_end`, as when
+    /// checking an append-only log against its published head.
+    pub fn verify_hash_chain(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        start: Number<Fp>,
+        items: &[Number<Fp>],
+        expected_end: Number<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let end = self.hash_chain(layouter, start, items)?;
+        self.assert_equal(
+            layouter.namespace(|| "chain reaches end"),
+            end,
+            expected_end,
+        )?;
+
+        Ok(())
+    }
+
+    /// Constrains `value` to be the Poseidon digest of the preimage whose little-endian
+    /// bits are `bits`, each of which is constrained to be boolean.
+    pub fn assert_commitment_of_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        value: Number<Fp>,
+        bits: &[Number<Fp>],
+    ) -> Result<(), PlaygroundError> {
+        for bit in bits {
+            self.assert_bool(layouter, bit.clone())?;
+        }
+
+        let preimage = self.from_bits(layouter, bits)?;
+        let digest = self.poseidon_hash(layouter.namespace(|| "hash preimage"), [preimage])?;
+        self.assert_equal(layouter.namespace(|| "value = digest"), value, digest)?;
+
+        Ok(())
+    }
+
+    /// Derives `n` pseudo-random challenges `Poseidon(seed, i)` for `i in 0..n` from a
+    /// single squeezed `seed`.
+    pub fn expand_challenges(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        seed: Number<Fp>,
+        n: usize,
+    ) -> Result<Vec<Number<Fp>>, PlaygroundError> {
+        (0..n)
+            .map(|i| {
+                let i_cell = self.load_constant(
+                    layouter.namespace(|| format!("load {i}")),
+                    Fp::from(i as u64),
+                )?;
+                let challenge = self.poseidon_hash(
+                    layouter.namespace(|| format!("challenge_{i}")),
+                    [seed.clone(), i_cell],
+                )?;
+                Ok::<_, PlaygroundError>(challenge)
+            })
+            .collect()
+    }
+
+    /// Returns the hiding commitment `Poseidon(value, blinding)`.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Number<Fp>,
+        blinding: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        Ok(self.poseidon_hash(layouter.namespace(|| "commitment"), [value, blinding])?)
+    }
+
+    /// Range-checks `value` to `n_bits` and returns its commitment `Poseidon(value, blinding)`,
+    /// as used for confidential amounts.
+    pub fn commit_with_range(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        value: Number<Fp>,
+        blinding: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        self.range_check(layouter, value.clone(), n_bits)?;
+        self.commit(layouter.namespace(|| "commit"), value, blinding)
+    }
+
+    /// Returns the mean `(xs[0] + ... + xs[n - 1]) / n`.
+    pub fn mean(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        xs: &[Number<Fp>],
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        if xs.is_empty() {
+            return Err(PlaygroundError::EmptyInput);
+        }
+
+        let sum = self.sum(layouter, xs)?;
+        let n = self.load_constant(layouter.namespace(|| "load n"), Fp::from(xs.len() as u64))?;
+        self.div(layouter, sum, n)
+    }
+
+    /// Returns `base^exp` for a constant `exp`, read as an integer below the modulus.
+    ///
+    /// Square-and-multiply from the most significant set bit of `exp` down: one `mul` per
+    /// bit after the first plus one per further set bit.
+    pub fn pow(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        base: Number<Fp>,
+        exp: Fp,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let mut pow: Option<Number<Fp>> = None;
+        for i in (0..Fp::NUM_BITS as usize).rev() {
+            if let Some(acc) = pow.take() {
+                pow = Some(self.mul(
+                    layouter.namespace(|| format!("square_{i}")),
+                    acc.clone(),
+                    acc,
+                )?);
+            }
+            if nth_bit(&exp, i) {
+                pow = Some(match pow.take() {
+                    Some(acc) => self.mul(
+                        layouter.namespace(|| format!("multiply_{i}")),
+                        acc,
+                        base.clone(),
+                    )?,
+                    None => base.clone(),
+                });
+            }
+        }
+
+        match pow {
+            Some(pow) => Ok(pow),
+            None => Ok(self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?),
+        }
+    }
+
+    /// Returns `a^-1` as `a^{p - 2}`, by Fermat's little theorem, mapping zero to zero.
+    ///
+    /// Unlike `div`, nothing is witnessed, so the result is fully determined by the gates.
+    /// The price is about 320 `mul`s, i.e. some 640 rows, against the 2 `mul`s of checking
+    /// a witnessed inverse.
+    ///
+    /// There is no `n_bits` parameter: the exponent is the constant `p - 2`, which spans the
+    /// full 255 bits of the field whatever the size of `a`, so the width is fixed.
+    pub fn inverse_by_fermat(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Number<Fp>,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        self.pow(layouter, a, -Fp::from(2))
+    }
+
+    /// Returns `base^exp` for a witnessed `exp` known to fit in `n_bits` bits.
+    ///
+    /// Square-and-multiply from the most significant bit of `exp` down, selecting the
+    /// multiplied accumulator wherever the bit is set.
+    pub fn pow_var(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        base: Number<Fp>,
+        exp: Number<Fp>,
+        n_bits: usize,
+    ) -> Result<Number<Fp>, PlaygroundError> {
+        let bits = self.to_bits(layouter, exp, n_bits)?;
+        let one = self.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?;
+
+        let pow = bits
+            .into_iter()
+            .enumerate()
+            .rev()
+            .try_fold(one, |acc, (i, bit)| {
+                let squared = self.mul(
+                    layouter.namespace(|| format!("square_{i}")),
+                    acc.clone(),
+                    acc,
+                )?;
+                let multiplied = self.mul(
+                    layouter.namespace(|| format!("multiply_{i}")),
+                    squared.clone(),
+                    base.clone(),
+                )?;
+                self.select(layouter, bit, multiplied, squared)
+            })?;
+
+        Ok(pow)
+    }
+}
+
+/// Returns the smallest `k` for which a circuit of type `C` using `rows` rows still leaves
+/// room for the rows reserved for zero knowledge.
+///
+/// The real prover blinds each advice column with `meta.blinding_factors()` random values
+/// in its last rows, plus a few rows for the permutation argument, so a circuit needs
+/// `2^k >= rows + meta.minimum_rows()`. The `MockProver` never blinds anything, so it
+/// cannot tell when `k` is too small for a real proof.
+pub fn minimum_k<C: Circuit<Fp>>(rows: usize) -> u32 {
+    let mut meta = ConstraintSystem::default();
+    C::configure(&mut meta);
+
+    (rows + meta.minimum_rows())
+        .next_power_of_two()
+        .trailing_zeros()
+}
+
+/// Returns a report of the gates of `meta`, one line each with the gate's name, its
+/// degree, its number of constraints and the columns it queries, for instance to see how
+/// much the Poseidon chip adds to `MyCircuit`.
+pub fn describe_constraints(meta: &ConstraintSystem<Fp>) -> String {
+    let mut report = format!("{} gates, degree {}\n", meta.gates().len(), meta.degree());
+
+    for gate in meta.gates() {
+        let degree = gate
+            .polynomials()
+            .iter()
+            .map(Expression::degree)
+            .max()
+            .unwrap_or(0);
+        let columns = gate
+            .polynomials()
+            .iter()
+            .flat_map(queried_columns)
+            .collect::<BTreeSet<_>>();
+
+        report += &format!(
+            "gate \"{}\": degree {degree}, {} constraints, columns {}\n",
+            gate.name(),
+            gate.polynomials().len(),
+            columns.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    report
+}
+
+/// Returns the names of the columns and selectors `poly` queries.
+fn queried_columns(poly: &Expression<Fp>) -> BTreeSet<String> {
+    let union = |mut a: BTreeSet<String>, b: BTreeSet<String>| {
+        a.extend(b);
+        a
+    };
+
+    poly.evaluate(
+        &|_| BTreeSet::new(),
+        &|selector| BTreeSet::from([format!("selector[{}]", selector.index())]),
+        &|query| BTreeSet::from([format!("fixed[{}]", query.column_index())]),
+        &|query| BTreeSet::from([format!("advice[{}]", query.column_index())]),
+        &|query| BTreeSet::from([format!("instance[{}]", query.column_index())]),
+        &|_| BTreeSet::new(),
+        &|a| a,
+        &union,
+        &union,
+        &|a, _| a,
+    )
+}
+
 /// The full circuit implementation.
 ///
 /// In this struct we store the private input variables. We use `Value<Fp>` because
@@ -398,9 +3533,96 @@ pub struct MyCircuit<Fp: Field> {
     // _marker: PhantomData<WIDTH>//: usize, RATE)>,
 }
 
-const WIDTH: usize = 3;
-const RATE: usize = 2;
-const L: usize = 1;
+pub const WIDTH: usize = 3;
+pub const RATE: usize = 2;
+pub const L: usize = 1;
+
+/// Absorbed ahead of the key by `FieldChip::prf`, so that PRF outputs never collide with
+/// plain two-input Poseidon digests: the ASCII bytes of "prf".
+pub const PRF_TAG: u64 = 0x70_72_66;
+
+/// The width of the addresses `FieldChip::derive_address` returns, as in Ethereum.
+pub const ADDRESS_BITS: usize = 160;
+
+/// The leaf of a sparse Merkle tree at a key that holds no value, as checked by
+/// `FieldChip::verify_smt_exclusion`. Poseidon has no known preimage of it.
+pub const SMT_EMPTY_LEAF: Fp = Fp::ZERO;
+
+// The inner product gate needs three advice columns, and `P128Pow5T3` has a single
+// capacity word.
+const _: () = assert!(
+    WIDTH >= 3 && WIDTH == RATE + 1,
+    "FieldChip needs WIDTH >= 3 and WIDTH == RATE + 1"
+);
+
+/// The absorbing `P128Pow5T3` sponge over `FieldChip`'s Poseidon chip that `MyCircuit`
+/// hashes `d` with. Other specs, widths or lengths still need the full `Sponge` type.
+pub type PlaygroundSponge<D = ConstantLength<L>> = Sponge<
+    Fp,
+    Pow5Chip<Fp, WIDTH, RATE>,
+    P128Pow5T3,
+    Absorbing<PaddedWord<Fp>, RATE>,
+    D,
+    WIDTH,
+    RATE,
+>;
+
+/// An initial capacity element for `WithCapacity`.
+pub trait CapacityIv {
+    /// Returns the element the capacity word of the sponge starts out as.
+    fn capacity() -> Fp;
+}
+
+/// The `ConstantLength<L>` domain with the capacity word of the sponge starting out as
+/// `I::capacity()` rather than the `L * 2^64` of `ConstantLength`, to match Poseidon
+/// instances specified elsewhere with another IV.
+///
+/// Works both natively, with `poseidon::primitives::Hash`, and in-circuit, with
+/// `new_sponge_with_capacity`.
+pub struct WithCapacity<I, const L: usize>(PhantomData<I>);
+
+impl<I, const L: usize> fmt::Debug for WithCapacity<I, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WithCapacity")
+            .field(&std::any::type_name::<I>())
+            .field(&L)
+            .finish()
+    }
+}
+
+impl<I: CapacityIv, const L: usize> Domain<Fp, RATE> for WithCapacity<I, L> {
+    type Padding = <ConstantLength<L> as Domain<Fp, RATE>>::Padding;
+
+    fn name() -> String {
+        format!("WithCapacity<{}, {L}>", std::any::type_name::<I>())
+    }
+
+    fn initial_capacity_element() -> Fp {
+        I::capacity()
+    }
+
+    fn padding(input_len: usize) -> Self::Padding {
+        <ConstantLength<L> as Domain<Fp, RATE>>::padding(input_len)
+    }
+}
+
+/// Returns a fresh `PlaygroundSponge` over the Poseidon chip of `chip`.
+pub fn new_sponge(
+    chip: &FieldChip<Fp, WIDTH, RATE>,
+    layouter: impl Layouter<Fp>,
+) -> Result<PlaygroundSponge, Error> {
+    let poseidon_chip = Pow5Chip::construct(chip.config().sponge_config()?);
+    Sponge::new(poseidon_chip, layouter)
+}
+
+/// Like `new_sponge`, but with the capacity word starting out as `I::capacity()`.
+pub fn new_sponge_with_capacity<I: CapacityIv>(
+    chip: &FieldChip<Fp, WIDTH, RATE>,
+    layouter: impl Layouter<Fp>,
+) -> Result<PlaygroundSponge<WithCapacity<I, L>>, Error> {
+    let poseidon_chip = Pow5Chip::construct(chip.config().sponge_config()?);
+    Sponge::new(poseidon_chip, layouter)
+}
 
 impl Circuit<Fp> for MyCircuit<Fp> {
     // Since we are using a single chip for everything, we can just reuse its config.
@@ -414,24 +3636,17 @@ impl Circuit<Fp> for MyCircuit<Fp> {
     }
 
     fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
-        // We create the two advice columns that FieldChip uses for I/O.
-        let advice = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
-        // let advice = [meta.advice_column(), meta.advice_column()];
+        // We create the WIDTH advice columns that FieldChip uses for I/O and hashing.
+        let advice = [(); WIDTH].map(|_| meta.advice_column());
 
         // We also need an instance column to store public inputs.
         let instance = meta.instance_column();
 
-        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
-        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+        let rc_a = [(); WIDTH].map(|_| meta.fixed_column());
+        let rc_b = [(); WIDTH].map(|_| meta.fixed_column());
         meta.enable_constant(rc_b[0]);
 
-        FieldChip::<Fp, WIDTH, RATE>::configure(
-            meta,
-            advice.try_into().unwrap(),
-            instance,
-            rc_a.try_into().unwrap(),
-            rc_b.try_into().unwrap(),
-        )
+        FieldChip::<Fp, WIDTH, RATE>::configure(meta, advice, instance, rc_a, rc_b)
     }
 
     fn synthesize(
@@ -439,29 +3654,23 @@ impl Circuit<Fp> for MyCircuit<Fp> {
         config: Self::Config,
         mut layouter: impl Layouter<Fp>,
     ) -> Result<(), Error> {
-        let field_chip = FieldChip::<Fp, WIDTH, RATE>::construct(config.clone(), ());
-        let config = config.sponge_config;
-        let poseidon_chip = Pow5Chip::<Fp, WIDTH, RATE>::construct(config);
-        let mut sponge: Sponge<
-            Fp,
-            Pow5Chip<Fp, WIDTH, RATE>,
-            P128Pow5T3,
-            Absorbing<halo2_gadgets::poseidon::PaddedWord<Fp>, RATE>,
-            ConstantLength<L>,
-            WIDTH,
-            RATE,
-        > = Sponge::new(poseidon_chip, layouter.namespace(|| "new sponge"))?;
-
-        // Load our private values into the circuit.
-        let a = field_chip.load_private(layouter.namespace(|| "load a"), self.a)?;
-        let b = field_chip.load_private(layouter.namespace(|| "load b"), self.b)?;
-        let c = field_chip.load_private(layouter.namespace(|| "load c"), self.c)?;
+        let field_chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+        let mut sponge = new_sponge(&field_chip, layouter.namespace(|| "new sponge"))?;
+
+        // Load our private values into the circuit, all in one row.
+        let [a, b, c]: [Number<Fp>; 3] = field_chip
+            .load_private_packed(
+                layouter.namespace(|| "load inputs"),
+                &[("a", self.a), ("b", self.b), ("c", self.c)],
+            )?
+            .try_into()
+            .unwrap();
 
         // Use `add_and_mul` to get `d = (a + b) * c`.
         let d = field_chip.add_and_mul(&mut layouter, a, b, c)?;
 
         // We need to pad to the multiple of RATE
-        let message = [d.0.clone()];
+        let message = [d.clone().into()];
         for (i, value) in message
             .into_iter()
             .map(PaddedWord::Message)
@@ -471,12 +3680,228 @@ impl Circuit<Fp> for MyCircuit<Fp> {
             sponge.absorb(layouter.namespace(|| format!("absorb_{i}")), value)?;
         }
 
-        // TODO figure out how to tackle multiple absorb-squeeze cycles, since current sponge requires calling `finish_absorbing`.
+        // This sponge can only squeeze after `finish_absorbing`; protocols with several
+        // absorb-squeeze rounds should use `Transcript` instead.
         let mut sponge = sponge.finish_absorbing(layouter.namespace(|| "finish absorbing"))?;
         let r = sponge.squeeze(layouter.namespace(|| "squeeze"))?;
 
-        // Expose the result as a public input to the circuit.
-        // TODO do something about the randomness r
+        // Expose the result and its digest as public inputs to the circuit.
+        field_chip.expose_public(layouter.namespace(|| "expose d"), d, Self::D_ROW)?;
+        field_chip.expose_public(
+            layouter.namespace(|| "expose digest"),
+            r.into(),
+            Self::DIGEST_ROW,
+        )
+    }
+}
+
+impl MyCircuit<Fp> {
+    /// The instance row holding `d = (a + b) * c`.
+    const D_ROW: usize = 0;
+    /// The instance row holding the Poseidon digest of `d`.
+    const DIGEST_ROW: usize = 1;
+
+    /// Returns the instance rows the circuit exposes, each with a name for what it holds,
+    /// in row order.
+    pub fn public_layout() -> Vec<(usize, &'static str)> {
+        vec![(Self::D_ROW, "d"), (Self::DIGEST_ROW, "digest")]
+    }
+
+    /// Returns the public inputs the circuit exposes for its witnesses: `d = (a + b) * c`,
+    /// followed by the Poseidon digest of `d`.
+    ///
+    /// Panics if any of the witnesses is unknown.
+    pub fn expected_public_inputs(&self) -> Vec<Fp> {
+        let CircuitInputs { a, b, c } = self.inputs().expect("public inputs need known witnesses");
+
+        let d = (a + b) * c;
+        let digest = halo2_gadgets::poseidon::primitives::Hash::<
+            _,
+            P128Pow5T3,
+            ConstantLength<L>,
+            WIDTH,
+            RATE,
+        >::init()
+        .hash([d]);
+        vec![d, digest]
+    }
+}
+
+/// The arithmetic half of `MyCircuit`: exposes `d = (a + b) * c` at row 0, without
+/// hashing it.
+///
+/// Its chip is configured without the Poseidon sponge, so it needs fewer columns and fits
+/// a smaller `k` than `MyCircuit`.
+#[derive(Default)]
+pub struct ArithmeticCircuit<Fp: Field> {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    c: Value<Fp>,
+}
+
+impl Circuit<Fp> for ArithmeticCircuit<Fp> {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let advice = [(); WIDTH].map(|_| meta.advice_column());
+        let instance = meta.instance_column();
+
+        FieldChip::<Fp, WIDTH, RATE>::configure_arithmetic(meta, advice, instance)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let [a, b, c]: [Number<Fp>; 3] = field_chip
+            .load_private_packed(
+                layouter.namespace(|| "load inputs"),
+                &[("a", self.a), ("b", self.b), ("c", self.c)],
+            )?
+            .try_into()
+            .unwrap();
+
+        let d = field_chip.add_and_mul(&mut layouter, a, b, c)?;
         field_chip.expose_public(layouter.namespace(|| "expose d"), d, 0)
     }
 }
+
+/// A variant of `MyCircuit` whose operation is picked by the public input at row 0: `0`
+/// computes `a + b` and `1` computes `a * b`, exposed at row 1.
+///
+/// Both results are always computed, and `select` keeps the requested one.
+#[derive(Default)]
+pub struct OpCircuit<Fp: Field> {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Circuit<Fp> for OpCircuit<Fp> {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let a = field_chip.load_private_named(layouter.namespace(|| "load a"), "a", self.a)?;
+        let b = field_chip.load_private_named(layouter.namespace(|| "load b"), "b", self.b)?;
+
+        // The operation is public, but still has to be a valid choice.
+        let op = field_chip.load_instance(layouter.namespace(|| "load op"), 0)?;
+        field_chip.assert_bool(&mut layouter, op.clone())?;
+
+        let sum = field_chip.add(layouter.namespace(|| "a + b"), a.clone(), b.clone())?;
+        let product = field_chip.mul(layouter.namespace(|| "a * b"), a, b)?;
+        let result = field_chip.select(&mut layouter, op, product, sum)?;
+
+        field_chip.expose_public(layouter.namespace(|| "expose result"), result, 1)
+    }
+}
+
+/// Proves `d = (a + b) * c` like `MyCircuit`, and also exposes the Poseidon commitment
+/// to `(a, b, c)`, so that the proof is bound to those exact inputs.
+///
+/// The public inputs are `d` at row 0 and the commitment at row 1.
+#[derive(Default)]
+pub struct CommittedCircuit<Fp: Field> {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    c: Value<Fp>,
+}
+
+impl From<CircuitInputs> for CommittedCircuit<Fp> {
+    fn from(inputs: CircuitInputs) -> Self {
+        Self {
+            a: Value::known(inputs.a),
+            b: Value::known(inputs.b),
+            c: Value::known(inputs.c),
+        }
+    }
+}
+
+impl CommittedCircuit<Fp> {
+    /// Returns the public inputs the circuit exposes for its witnesses: `d` followed by
+    /// the commitment.
+    ///
+    /// Panics if any of the witnesses is unknown.
+    pub fn expected_public_inputs(&self) -> Vec<Fp> {
+        let mut public_inputs = vec![];
+        self.a.zip(self.b).zip(self.c).map(|((a, b), c)| {
+            let commitment = halo2_gadgets::poseidon::primitives::Hash::<
+                _,
+                P128Pow5T3,
+                ConstantLength<3>,
+                WIDTH,
+                RATE,
+            >::init()
+            .hash([a, b, c]);
+            public_inputs = vec![(a + b) * c, commitment];
+        });
+
+        assert!(
+            !public_inputs.is_empty(),
+            "public inputs need known witnesses"
+        );
+        public_inputs
+    }
+}
+
+impl Circuit<Fp> for CommittedCircuit<Fp> {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let [a, b, c]: [Number<Fp>; 3] = field_chip
+            .load_private_packed(
+                layouter.namespace(|| "load inputs"),
+                &[("a", self.a), ("b", self.b), ("c", self.c)],
+            )?
+            .try_into()
+            .unwrap();
+
+        let d = field_chip.add_and_mul(&mut layouter, a.clone(), b.clone(), c.clone())?;
+        field_chip.expose_public(layouter.namespace(|| "expose d"), d, 0)?;
+
+        Ok(field_chip.bind_witness(&mut layouter, [a, b, c], 1)?)
+    }
+}
@@ -1,18 +1,17 @@
-use halo2_playground::MyCircuit;
-use halo2_proofs::dev::CircuitLayout;
-use plotters::prelude::*;
+use std::path::PathBuf;
+
+use halo2_playground::{render_layout, MyCircuit};
 
 use halo2curves::pasta::Fp;
+
+/// Renders the layout of `MyCircuit` to the path given as the first argument, as an SVG
+/// or a bitmap depending on its extension.
 fn main() {
-    let drawing_area =
-        BitMapBackend::new("example-circuit-layout.png", (1024, 768)).into_drawing_area();
-    drawing_area.fill(&WHITE).unwrap();
-    let drawing_area = drawing_area
-        .titled("Example Circuit Layout", ("sans-serif", 60))
-        .unwrap();
+    let path = std::env::args().nth(1).map_or_else(
+        || PathBuf::from("example-circuit-layout.png"),
+        PathBuf::from,
+    );
     let circuit: MyCircuit<Fp> = MyCircuit::default();
     let k = 6; // Suitable size for MyCircuit
-    CircuitLayout::default()
-        .render(k, &circuit, &drawing_area)
-        .unwrap();
+    render_layout(&circuit, k, "Example Circuit Layout", &path).unwrap();
 }
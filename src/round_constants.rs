@@ -0,0 +1,101 @@
+//! Poseidon parameter sets supplied as explicit constant tables.
+//!
+//! `P128Pow5T3` generates its round constants with the Grain LFSR from the number of
+//! rounds. Parameter sets generated elsewhere, for instance for another field, instead
+//! come as tables, which a `ConstantTable` hands to `Pow5Chip` through `TableSpec`.
+
+use std::{fmt, marker::PhantomData};
+
+use halo2_gadgets::poseidon::{primitives::Spec, Pow5Chip, Pow5Config};
+use halo2_proofs::{
+    arithmetic::Field,
+    plonk::{Advice, Column, ConstraintSystem, Fixed},
+};
+use halo2curves::pasta::Fp;
+
+use crate::{PlaygroundError, RATE, WIDTH};
+
+/// A Poseidon parameter set with an x^5 S-box, given by its constant tables.
+pub trait ConstantTable {
+    /// The number of full rounds, split evenly before and after the partial rounds.
+    const FULL_ROUNDS: usize;
+    /// The number of partial rounds.
+    const PARTIAL_ROUNDS: usize;
+
+    /// Returns the round constants, one row per round.
+    fn round_constants() -> Vec<[Fp; WIDTH]>;
+
+    /// Returns the MDS matrix.
+    fn mds() -> [[Fp; WIDTH]; WIDTH];
+
+    /// Returns the inverse of the MDS matrix.
+    fn mds_inv() -> [[Fp; WIDTH]; WIDTH];
+}
+
+/// The Poseidon `Spec` described by the constant table `T`.
+pub struct TableSpec<T>(PhantomData<T>);
+
+impl<T> fmt::Debug for TableSpec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TableSpec")
+            .field(&std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+impl<T: ConstantTable> Spec<Fp, WIDTH, RATE> for TableSpec<T> {
+    fn full_rounds() -> usize {
+        T::FULL_ROUNDS
+    }
+
+    fn partial_rounds() -> usize {
+        T::PARTIAL_ROUNDS
+    }
+
+    fn sbox(val: Fp) -> Fp {
+        val.pow_vartime([5])
+    }
+
+    // Only read by the default `constants`, which we override with the table's matrices.
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> (Vec<[Fp; WIDTH]>, [[Fp; WIDTH]; WIDTH], [[Fp; WIDTH]; WIDTH]) {
+        (T::round_constants(), T::mds(), T::mds_inv())
+    }
+}
+
+/// Configures a `Pow5Chip` for the parameter set `T`.
+///
+/// `Pow5Chip` reads one row of round constants per round without checking how many there
+/// are, so a short table would only panic once a permutation is synthesized; we check the
+/// table against its number of rounds here instead. The chip also processes rounds in
+/// pairs, so both round counts must be even.
+pub fn configure_poseidon_with_table<T: ConstantTable>(
+    meta: &mut ConstraintSystem<Fp>,
+    state: [Column<Advice>; WIDTH],
+    partial_sbox: Column<Advice>,
+    rc_a: [Column<Fixed>; WIDTH],
+    rc_b: [Column<Fixed>; WIDTH],
+) -> Result<Pow5Config<Fp, WIDTH, RATE>, PlaygroundError> {
+    let expected = T::FULL_ROUNDS + T::PARTIAL_ROUNDS;
+    let actual = T::round_constants().len();
+    if actual != expected {
+        return Err(PlaygroundError::LengthMismatch { expected, actual });
+    }
+    if T::FULL_ROUNDS % 2 != 0 || T::PARTIAL_ROUNDS % 2 != 0 {
+        return Err(PlaygroundError::OddRounds {
+            full_rounds: T::FULL_ROUNDS,
+            partial_rounds: T::PARTIAL_ROUNDS,
+        });
+    }
+
+    Ok(Pow5Chip::configure::<TableSpec<T>>(
+        meta,
+        state,
+        partial_sbox,
+        rc_a,
+        rc_b,
+    ))
+}
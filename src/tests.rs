@@ -1,25 +1,237 @@
-use halo2_proofs::circuit::Value;
-use halo2curves::ff::Field;
+use std::cell::RefCell;
 
-use crate::MyCircuit;
+use halo2_gadgets::poseidon::{
+    primitives::{self as poseidon, ConstantLength, Domain, P128Pow5T3, Spec},
+    Hash as PoseidonHash, PaddedWord, Pow5Chip, Pow5Config,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::{CellValue, MockProver},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error,
+        Instance, ProvingKey, VerifyingKey,
+    },
+    poly::{
+        commitment::ParamsProver,
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::ProverIPA,
+        },
+    },
+    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    SerdeFormat,
+};
+use halo2curves::{
+    ff::{Field, PrimeField},
+    pasta::{EqAffine, Fp},
+};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+
+use crate::{
+    configure_poseidon_with_table, decode_instances, describe_constraints, encode_instances,
+    minimum_k, new_sponge_with_capacity, render_layout, verify_from_bytes, verify_proofs, vk_hash,
+    ArithmeticCircuit, CapacityIv, CircuitBuilder, CircuitInputs, CircuitTranscript, ColumnStats,
+    CommittedCircuit, ConstantTable, FieldChip, FieldConfig, MyCircuit, Number, Op, OpCircuit,
+    PlaygroundError, PoseidonSpec, TableSpec, Transcript, TranscriptSponge, VerifyStrategy,
+    WithCapacity, ADDRESS_BITS, L, PRF_TAG, RATE, WIDTH,
+};
+
+/// A piece of circuit logic under test, synthesized against a `FieldChip` configured
+/// exactly like the one in `MyCircuit`.
+trait Gadget: Default {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError>;
+}
+
+/// Wraps a `Gadget` into a full circuit so it can be run through the `MockProver`.
+///
+/// `Circuit::synthesize` can only return a `plonk::Error`, so the `PlaygroundError` the
+/// gadget failed with is kept aside for `gadget_error` to match on.
+#[derive(Default)]
+struct GadgetCircuit<G: Gadget> {
+    gadget: G,
+    error: RefCell<Option<PlaygroundError>>,
+}
+
+impl<G: Gadget> GadgetCircuit<G> {
+    fn new(gadget: G) -> Self {
+        Self {
+            gadget,
+            error: RefCell::default(),
+        }
+    }
+}
+
+impl<G: Gadget> Circuit<Fp> for GadgetCircuit<G> {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+        self.gadget.synthesize(&chip, layouter).map_err(|err| {
+            *self.error.borrow_mut() = Some(err);
+            Error::Synthesis
+        })
+    }
+}
+
+/// Runs `gadget` through the `MockProver` against the given public inputs and returns
+/// whether its constraints are satisfied.
+///
+/// Panics if synthesis fails, so that a witness the gadget refuses is never mistaken for
+/// one the constraints reject; such cases belong to `gadget_error`.
+fn verify_gadget<G: Gadget>(k: u32, gadget: G, public_inputs: Vec<Fp>) -> bool {
+    let circuit = GadgetCircuit::new(gadget);
+    match MockProver::run(k, &circuit, vec![public_inputs]) {
+        Ok(prover) => prover.verify().is_ok(),
+        Err(err) => match circuit.error.take() {
+            Some(err) => panic!("gadget failed to synthesize: {err}"),
+            None => panic!("circuit failed to synthesize: {err}"),
+        },
+    }
+}
+
+/// Synthesizes `gadget` with the `MockProver` and returns the error it fails with.
+///
+/// Panics if synthesis succeeds.
+fn gadget_error<G: Gadget>(k: u32, gadget: G) -> PlaygroundError {
+    let circuit = GadgetCircuit::new(gadget);
+    let err = MockProver::run(k, &circuit, vec![vec![]])
+        .err()
+        .expect("gadget synthesized");
+    circuit.error.take().unwrap_or(PlaygroundError::Plonk(err))
+}
+
+/// Reference Poseidon hash computed outside of the circuit.
+fn poseidon_native<const L: usize>(inputs: [Fp; L]) -> Fp {
+    poseidon::Hash::<_, P128Pow5T3, ConstantLength<L>, WIDTH, RATE>::init().hash(inputs)
+}
+
+/// Creates a real IPA proof of `circuit` against `public_inputs`, or `None` if the prover
+/// rejects the witness, as it does when a lookup input is missing from its table.
+///
+/// Panics on any other error, such as a synthesis failure or running out of rows, so that
+/// it is never mistaken for a rejected witness.
+fn create_real_proof<C: Circuit<Fp>>(
+    params: &ParamsIPA<EqAffine>,
+    pk: &ProvingKey<EqAffine>,
+    circuit: C,
+    public_inputs: &[Fp],
+) -> Option<Vec<u8>> {
+    let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+    match create_proof::<IPACommitmentScheme<EqAffine>, ProverIPA<EqAffine>, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[&[public_inputs]],
+        OsRng,
+        &mut transcript,
+    ) {
+        Ok(()) => Some(transcript.finalize()),
+        Err(Error::ConstraintSystemFailure) => None,
+        Err(err) => panic!("failed to create a proof: {err}"),
+    }
+}
+
+/// Checks whether `proof` verifies against `public_inputs`.
+fn verify_real_proof(
+    params: &ParamsIPA<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    proof: &[u8],
+    public_inputs: &[Fp],
+) -> bool {
+    verify_real_proof_with(params, vk, proof, public_inputs, VerifyStrategy::default())
+}
+
+/// Checks whether `proof` verifies against `public_inputs`, using `strategy`.
+fn verify_real_proof_with(
+    params: &ParamsIPA<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    proof: &[u8],
+    public_inputs: &[Fp],
+    strategy: VerifyStrategy,
+) -> bool {
+    verify_proofs(params, vk, &[(proof, &[public_inputs])], strategy).is_ok()
+}
+
+/// Generates keys for `circuit`, creates a real IPA proof and checks whether it verifies
+/// against `public_inputs`.
+fn prove_and_verify<C: Circuit<Fp>>(k: u32, circuit: C, public_inputs: &[Fp]) -> bool {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let vk = keygen_vk(&params, &circuit).unwrap();
+    let pk = keygen_pk(&params, vk, &circuit).unwrap();
+
+    create_real_proof(&params, &pk, circuit, public_inputs).map_or(false, |proof| {
+        verify_real_proof(&params, pk.get_vk(), &proof, public_inputs)
+    })
+}
+
+/// Checks `circuit` against `public_inputs` with both the `MockProver` and a real IPA
+/// proof, panicking if they disagree, and returns their common verdict.
+///
+/// The `MockProver` can be more lenient than the real system, for instance about rows
+/// that collide with the blinding rows.
+///
+/// Panics if the circuit fails to synthesize at `k`, so that only an actual verification
+/// failure counts as a rejection.
+fn assert_mock_and_real_agree<C: Circuit<Fp>>(circuit: C, k: u32, public_inputs: &[Fp]) -> bool {
+    let mock = MockProver::run(k, &circuit, vec![public_inputs.to_vec()])
+        .unwrap_or_else(|err| panic!("circuit failed to synthesize at k = {k}: {err}"))
+        .verify()
+        .is_ok();
+    let real = prove_and_verify(k, circuit, public_inputs);
+
+    assert_eq!(
+        mock,
+        real,
+        "MockProver {} but the real prover {} at k = {k}",
+        if mock { "accepts" } else { "rejects" },
+        if real { "accepts" } else { "rejects" },
+    );
+    mock
+}
+
+/// Returns the number of rows up to and including the last one with an assigned advice
+/// cell.
+fn used_rows(prover: &MockProver<Fp>) -> usize {
+    prover
+        .advice()
+        .iter()
+        .filter_map(|column| {
+            column
+                .iter()
+                .rposition(|cell| matches!(cell, CellValue::Assigned(_)))
+        })
+        .max()
+        .map_or(0, |row| row + 1)
+}
 
 #[test]
 fn test_circuit() {
-    use halo2_proofs::dev::MockProver;
-    use halo2curves::pasta::Fp;
-    use rand_core::OsRng;
-
     // ANCHOR: test-circuit
     // The number of rows in our circuit cannot exceed 2^k. Since our example
     // circuit is very small, we can pick a very small value here.
     let k = 6;
 
-    // Prepare the private and public inputs to the circuit!
+    // Prepare the private inputs to the circuit!
     let rng = OsRng;
     let a = Fp::random(rng);
     let b = Fp::random(rng);
     let c = Fp::random(rng);
-    let d = (a + b) * c;
 
     // Instantiate the circuit with the private inputs.
     let circuit = MyCircuit {
@@ -29,8 +241,10 @@ fn test_circuit() {
     };
 
     // Arrange the public input. We expose the multiplication result in row 0
-    // of the instance column, so we position it there in our public inputs.
-    let mut public_inputs = vec![d];
+    // of the instance column and its digest in row 1, so we position them there in our
+    // public inputs.
+    let mut public_inputs = vec![(a + b) * c, poseidon_native([(a + b) * c])];
+    assert_eq!(public_inputs, circuit.expected_public_inputs());
 
     // Given the correct public input, our circuit will verify.
     let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()]).unwrap();
@@ -49,3 +263,4805 @@ fn test_poseidon() {
     // test that natively calling a poseidon on some values and then verying the result in the circuit passses
     assert!(true);
 }
+
+#[derive(Default)]
+struct MeanGadget {
+    xs: Vec<Value<Fp>>,
+}
+
+impl Gadget for MeanGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let xs = self
+            .xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| chip.load_private(layouter.namespace(|| format!("load x_{i}")), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mean = chip.mean(&mut layouter, &xs)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose mean"), mean, 0)?)
+    }
+}
+
+#[test]
+fn test_mean() {
+    let xs = [3, 5, 7, 9].map(|x| Value::known(Fp::from(x))).to_vec();
+
+    assert!(verify_gadget(
+        6,
+        MeanGadget { xs: xs.clone() },
+        vec![Fp::from(6)]
+    ));
+    assert!(!verify_gadget(6, MeanGadget { xs }, vec![Fp::from(7)]));
+
+    // The mean of an empty list is undefined, so synthesis refuses it.
+    assert!(matches!(
+        gadget_error(6, MeanGadget { xs: vec![] }),
+        PlaygroundError::EmptyInput
+    ));
+}
+
+#[derive(Default)]
+struct SignBitGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for SignBitGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let sign = chip.sign_bit(&mut layouter, a, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose sign"), sign, 0)?)
+    }
+}
+
+#[test]
+fn test_sign_bit() {
+    // -125 as an 8-bit two's-complement integer.
+    let negative = Value::known(Fp::from(0b1000_0011));
+    assert!(verify_gadget(
+        8,
+        SignBitGadget { a: negative },
+        vec![Fp::ONE]
+    ));
+    assert!(!verify_gadget(
+        8,
+        SignBitGadget { a: negative },
+        vec![Fp::ZERO]
+    ));
+
+    let positive = Value::known(Fp::from(0b0111_1111));
+    assert!(verify_gadget(
+        8,
+        SignBitGadget { a: positive },
+        vec![Fp::ZERO]
+    ));
+    assert!(!verify_gadget(
+        8,
+        SignBitGadget { a: positive },
+        vec![Fp::ONE]
+    ));
+}
+
+/// The "hello world" of zero knowledge: proves knowledge of a private preimage of a
+/// public Poseidon digest.
+#[derive(Default)]
+struct PreimageCircuit {
+    preimage: Value<Fp>,
+}
+
+impl Circuit<Fp> for PreimageCircuit {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let preimage = chip.load_private(layouter.namespace(|| "load preimage"), self.preimage)?;
+        let digest = chip.poseidon_hash(layouter.namespace(|| "hash preimage"), [preimage])?;
+        chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)
+    }
+}
+
+#[test]
+fn test_preimage_proof() {
+    let preimage = Fp::random(OsRng);
+    let digest = poseidon_native([preimage]);
+
+    let circuit = || PreimageCircuit {
+        preimage: Value::known(preimage),
+    };
+    assert!(assert_mock_and_real_agree(circuit(), 7, &[digest]));
+    assert!(!assert_mock_and_real_agree(
+        circuit(),
+        7,
+        &[digest + Fp::ONE]
+    ));
+}
+
+#[derive(Default)]
+struct HashToBitsGadget {
+    inputs: [Value<Fp>; 2],
+    n_bits: usize,
+}
+
+impl Gadget for HashToBitsGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.inputs[0])?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.inputs[1])?;
+
+        let bits = chip.hash_to_bits(&mut layouter, [x, y], self.n_bits)?;
+        for (i, bit) in bits.into_iter().enumerate() {
+            chip.expose_public(layouter.namespace(|| format!("expose bit_{i}")), bit, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_hash_to_bits() {
+    let inputs = [Fp::from(1), Fp::from(2)];
+    let n_bits = 8;
+
+    let digest = poseidon_native(inputs).to_repr();
+    let mut bits = (0..n_bits)
+        .map(|i| Fp::from(((digest[i / 8] >> (i % 8)) & 1) as u64))
+        .collect::<Vec<_>>();
+
+    let gadget = || HashToBitsGadget {
+        inputs: inputs.map(Value::known),
+        n_bits,
+    };
+    assert!(verify_gadget(11, gadget(), bits.clone()));
+
+    bits[0] = Fp::ONE - bits[0];
+    assert!(!verify_gadget(11, gadget(), bits));
+}
+
+/// Returns the 255 little-endian bits of the integer with little-endian bytes `repr`.
+fn repr_bits(repr: [u8; 32]) -> Vec<Fp> {
+    (0..Fp::NUM_BITS as usize)
+        .map(|i| Fp::from(((repr[i / 8] >> (i % 8)) & 1) as u64))
+        .collect()
+}
+
+/// Returns the bytes of the integer `a + p`, the non-canonical encoding of `a`, if it still
+/// fits in 255 bits.
+fn repr_plus_modulus(a: Fp) -> Option<[u8; 32]> {
+    add_modulus(a.to_repr()).filter(|sum| sum[31] >> 7 == 0)
+}
+
+/// Returns the little-endian bytes of the integer `repr + p`, if it still fits in 256 bits.
+fn add_modulus(repr: [u8; 32]) -> Option<[u8; 32]> {
+    let mut sum = [0; 32];
+    let mut carry = 1;
+    for (i, (x, y)) in repr.iter().zip((-Fp::ONE).to_repr()).enumerate() {
+        let word = *x as u16 + y as u16 + carry;
+        sum[i] = word as u8;
+        carry = word >> 8;
+    }
+    (carry == 0).then_some(sum)
+}
+
+/// Hashes `inputs` like `hash_to_bits`, but constrains the digest against the
+/// decomposition `bits` given by the test rather than the one the chip witnesses.
+#[derive(Default)]
+struct DigestBitsGadget {
+    inputs: [Value<Fp>; 2],
+    bits: Vec<Value<Fp>>,
+}
+
+impl Gadget for DigestBitsGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.inputs[0])?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.inputs[1])?;
+        let bits = chip.load_private_iter(
+            layouter.namespace(|| "load bits"),
+            self.bits.iter().copied(),
+        )?;
+
+        let digest = chip.poseidon_hash(layouter.namespace(|| "hash inputs"), [x, y])?;
+        chip.assert_bits(&mut layouter, digest, &bits)
+    }
+}
+
+/// Returns random inputs whose digest `d` has a second decomposition, as the bits of
+/// `d + p`, along with both decompositions.
+fn digest_with_two_decompositions() -> ([Fp; 2], Vec<Fp>, Vec<Fp>) {
+    loop {
+        let inputs = [(); 2].map(|_| Fp::random(OsRng));
+        let digest = poseidon_native(inputs);
+        if let Some(forged) = repr_plus_modulus(digest) {
+            return (inputs, repr_bits(digest.to_repr()), repr_bits(forged));
+        }
+    }
+}
+
+#[test]
+fn test_hash_to_bits_canonical() {
+    let (inputs, bits, forged) = digest_with_two_decompositions();
+    let gadget = |bits: Vec<Fp>| DigestBitsGadget {
+        inputs: inputs.map(Value::known),
+        bits: bits.into_iter().map(Value::known).collect(),
+    };
+
+    // The bits of `d + p` recompose to `d` in the field, but exceed the modulus.
+    assert_ne!(bits[0], forged[0]);
+    assert!(verify_gadget(11, gadget(bits), vec![]));
+    assert!(!verify_gadget(11, gadget(forged), vec![]));
+}
+
+#[derive(Default)]
+struct DebugGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for DebugGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.a)?;
+        let c = chip.add(layouter.namespace(|| "a + b"), a.clone(), b.clone())?;
+
+        assert!(format!("{a:?}").starts_with("Number(AssignedCell"));
+        a.value_eq(&b).assert_if_known(|eq| *eq);
+        a.value_eq(&c).assert_if_known(|eq| !*eq);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_number_debug() {
+    let gadget = DebugGadget {
+        a: Value::known(Fp::from(3)),
+    };
+    assert!(verify_gadget(6, gadget, vec![]));
+}
+
+#[derive(Default)]
+struct LagrangeGadget {
+    points: Vec<(Value<Fp>, Value<Fp>)>,
+    x: Value<Fp>,
+}
+
+impl Gadget for LagrangeGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let points = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| {
+                let x = chip.load_private(layouter.namespace(|| format!("load x_{i}")), *x)?;
+                let y = chip.load_private(layouter.namespace(|| format!("load y_{i}")), *y)?;
+                Ok::<_, Error>((x, y))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.x)?;
+
+        let y = chip.lagrange_eval(&mut layouter, &points, x)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose y"), y, 0)?)
+    }
+}
+
+#[test]
+fn test_lagrange_eval() {
+    // Points on p(x) = 2x^2 + 3x + 1.
+    let p = |x: u64| Fp::from(2 * x * x + 3 * x + 1);
+    let points = [1, 2, 3]
+        .map(|x| (Value::known(Fp::from(x)), Value::known(p(x))))
+        .to_vec();
+
+    let gadget = LagrangeGadget {
+        points: points.clone(),
+        x: Value::known(Fp::from(4)),
+    };
+    assert!(verify_gadget(7, gadget, vec![p(4)]));
+
+    let gadget = LagrangeGadget {
+        points: points.clone(),
+        x: Value::known(Fp::from(4)),
+    };
+    assert!(!verify_gadget(7, gadget, vec![p(4) + Fp::ONE]));
+
+    // Duplicate x-coordinates make the interpolation ill-defined.
+    let mut duplicate = points;
+    duplicate[2].0 = duplicate[0].0;
+    let gadget = LagrangeGadget {
+        points: duplicate,
+        x: Value::known(Fp::from(4)),
+    };
+    assert!(matches!(
+        gadget_error(7, gadget),
+        PlaygroundError::DuplicateInput
+    ));
+}
+
+#[derive(Default)]
+struct HashBatchGadget {
+    pairs: Vec<[Value<Fp>; 2]>,
+}
+
+impl Gadget for HashBatchGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let pairs = self
+            .pairs
+            .iter()
+            .enumerate()
+            .map(|(i, [x, y])| {
+                let x = chip.load_private(layouter.namespace(|| format!("load x_{i}")), *x)?;
+                let y = chip.load_private(layouter.namespace(|| format!("load y_{i}")), *y)?;
+                Ok::<_, Error>([x, y])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let digests = chip.poseidon_hash_batch(&mut layouter, &pairs)?;
+        for (i, digest) in digests.into_iter().enumerate() {
+            chip.expose_public(
+                layouter.namespace(|| format!("expose digest_{i}")),
+                digest,
+                i,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_poseidon_hash_batch() {
+    let pairs = (0..8)
+        .map(|i| [Fp::from(2 * i), Fp::from(2 * i + 1)])
+        .collect::<Vec<_>>();
+    let mut digests = pairs
+        .iter()
+        .map(|pair| poseidon_native(*pair))
+        .collect::<Vec<_>>();
+
+    let gadget = || HashBatchGadget {
+        pairs: pairs.iter().map(|pair| pair.map(Value::known)).collect(),
+    };
+    assert!(verify_gadget(9, gadget(), digests.clone()));
+
+    digests.swap(0, 1);
+    assert!(!verify_gadget(9, gadget(), digests));
+}
+
+#[derive(Default)]
+struct PopcountGeGadget {
+    bits: Vec<Value<Fp>>,
+    k: usize,
+}
+
+impl Gadget for PopcountGeGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let bits = self
+            .bits
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| chip.load_private(layouter.namespace(|| format!("load bit_{i}")), *bit))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        chip.assert_popcount_ge(&mut layouter, &bits, self.k)
+    }
+}
+
+#[test]
+fn test_assert_popcount_ge() {
+    let bits = [1, 0, 1, 1, 0]
+        .map(|bit| Value::known(Fp::from(bit)))
+        .to_vec();
+
+    let gadget = PopcountGeGadget {
+        bits: bits.clone(),
+        k: 2,
+    };
+    assert!(verify_gadget(7, gadget, vec![]));
+
+    let gadget = PopcountGeGadget { bits, k: 4 };
+    assert!(!verify_gadget(7, gadget, vec![]));
+}
+
+#[derive(Default)]
+struct LoadIterGadget {
+    n: u64,
+}
+
+impl Gadget for LoadIterGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = (0..self.n).map(|i| Value::known(Fp::from(i)));
+        let xs = chip.load_private_iter(layouter.namespace(|| "load xs"), values)?;
+        assert_eq!(xs.len() as u64, self.n);
+
+        let sum = chip.sum(&mut layouter, &xs)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose sum"), sum, 0)?)
+    }
+}
+
+#[test]
+fn test_load_private_iter() {
+    assert!(verify_gadget(
+        6,
+        LoadIterGadget { n: 5 },
+        vec![Fp::from(10)]
+    ));
+    assert!(!verify_gadget(
+        6,
+        LoadIterGadget { n: 5 },
+        vec![Fp::from(11)]
+    ));
+}
+
+#[derive(Default)]
+struct AffineInstanceGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for AffineInstanceGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        Ok(chip.assert_affine_instance(&mut layouter, a, Fp::from(2), Fp::ONE, 0)?)
+    }
+}
+
+#[test]
+fn test_assert_affine_instance() {
+    let gadget = || AffineInstanceGadget {
+        a: Value::known(Fp::from(5)),
+    };
+    assert!(verify_gadget(6, gadget(), vec![Fp::from(11)]));
+    assert!(!verify_gadget(6, gadget(), vec![Fp::from(10)]));
+}
+
+#[derive(Default)]
+struct DivByZeroGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for DivByZeroGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let zero = chip.load_private(layouter.namespace(|| "load zero"), Value::known(Fp::ZERO))?;
+
+        chip.div(&mut layouter, a, zero)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_div_by_zero() {
+    let gadget = DivByZeroGadget {
+        a: Value::known(Fp::from(7)),
+    };
+    assert!(matches!(
+        gadget_error(6, gadget),
+        PlaygroundError::DivisionByZero
+    ));
+}
+
+#[derive(Default)]
+struct NullifierGadget {
+    secret: Value<Fp>,
+    indices: Vec<Value<Fp>>,
+}
+
+impl Gadget for NullifierGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let secret = chip.load_private(layouter.namespace(|| "load secret"), self.secret)?;
+
+        for (i, index) in self.indices.iter().enumerate() {
+            let index =
+                chip.load_private(layouter.namespace(|| format!("load index_{i}")), *index)?;
+            chip.derive_nullifier(
+                &mut layouter.namespace(|| format!("nullifier_{i}")),
+                secret.clone(),
+                index,
+                i,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_derive_nullifier() {
+    let secret = Fp::random(OsRng);
+    let nullifiers = [0, 1].map(|index| poseidon_native([secret, Fp::from(index)]));
+    assert_ne!(nullifiers[0], nullifiers[1]);
+
+    let gadget = || NullifierGadget {
+        secret: Value::known(secret),
+        indices: vec![Value::known(Fp::from(0)), Value::known(Fp::from(1))],
+    };
+    assert!(verify_gadget(8, gadget(), nullifiers.to_vec()));
+    assert!(!verify_gadget(
+        8,
+        gadget(),
+        vec![nullifiers[0], nullifiers[0]]
+    ));
+}
+
+/// Composes an arithmetic chip and a hashing chip over disjoint columns, both exposing
+/// their outputs through the same instance column.
+#[derive(Default)]
+struct ComposedCircuit {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    c: Value<Fp>,
+    preimage: Value<Fp>,
+}
+
+impl Circuit<Fp> for ComposedCircuit {
+    type Config = (FieldConfig<Fp, WIDTH, RATE>, FieldConfig<Fp, WIDTH, RATE>);
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+
+        let arithmetic = FieldChip::<Fp, WIDTH, RATE>::configure_with_fresh_columns(meta, instance);
+        let hashing = FieldChip::<Fp, WIDTH, RATE>::configure_with_fresh_columns(meta, instance);
+
+        (arithmetic, hashing)
+    }
+
+    fn synthesize(
+        &self,
+        (arithmetic, hashing): Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let arithmetic = FieldChip::<Fp, WIDTH, RATE>::construct(arithmetic, ());
+        let hashing = FieldChip::<Fp, WIDTH, RATE>::construct(hashing, ());
+
+        let a = arithmetic.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = arithmetic.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let c = arithmetic.load_private(layouter.namespace(|| "load c"), self.c)?;
+        let d = arithmetic.add_and_mul(&mut layouter, a, b, c)?;
+        arithmetic.expose_public(layouter.namespace(|| "expose d"), d, 0)?;
+
+        let preimage =
+            hashing.load_private(layouter.namespace(|| "load preimage"), self.preimage)?;
+        let digest = hashing.poseidon_hash(layouter.namespace(|| "hash preimage"), [preimage])?;
+        hashing.expose_public(layouter.namespace(|| "expose digest"), digest, 1)
+    }
+}
+
+#[test]
+fn test_composed_chips() {
+    let [a, b, c, preimage] = [(); 4].map(|_| Fp::random(OsRng));
+    let d = (a + b) * c;
+    let digest = poseidon_native([preimage]);
+
+    let circuit = || ComposedCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+        preimage: Value::known(preimage),
+    };
+
+    assert!(assert_mock_and_real_agree(circuit(), 7, &[d, digest]));
+    assert!(!assert_mock_and_real_agree(
+        circuit(),
+        7,
+        &[d, digest + Fp::ONE]
+    ));
+}
+
+/// Reference Poseidon Merkle tree over `leaves`, returned level by level from the leaves
+/// up to the root.
+fn merkle_levels_native(leaves: &[Fp]) -> Vec<Vec<Fp>> {
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), Fp::ZERO);
+
+    let mut levels = vec![level];
+    while levels.last().unwrap().len() > 1 {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| poseidon_native([pair[0], pair[1]]))
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+/// Reference sibling path of the leaf at `index`, from the bottom of the tree up.
+fn merkle_path_native(levels: &[Vec<Fp>], index: usize) -> Vec<Fp> {
+    levels[..levels.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(depth, level)| level[(index >> depth) ^ 1])
+        .collect()
+}
+
+#[derive(Default)]
+struct VectorCommitmentGadget {
+    xs: Vec<Value<Fp>>,
+    index: usize,
+    siblings: Vec<Value<Fp>>,
+}
+
+impl Gadget for VectorCommitmentGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let xs =
+            chip.load_private_iter(layouter.namespace(|| "load xs"), self.xs.iter().copied())?;
+        let siblings = chip.load_private_iter(
+            layouter.namespace(|| "load siblings"),
+            self.siblings.iter().copied(),
+        )?;
+
+        let root = chip.vector_commitment(&mut layouter, &xs)?;
+        chip.open_commitment(
+            &mut layouter,
+            xs[self.index].clone(),
+            self.index,
+            &siblings,
+            root.clone(),
+        )?;
+
+        Ok(chip.expose_public(layouter.namespace(|| "expose root"), root, 0)?)
+    }
+}
+
+#[test]
+fn test_vector_commitment() {
+    let xs = [1, 2, 3, 4].map(Fp::from);
+    let levels = merkle_levels_native(&xs);
+    let root = levels.last().unwrap()[0];
+
+    let gadget = |siblings: Vec<Fp>| VectorCommitmentGadget {
+        xs: xs.iter().copied().map(Value::known).collect(),
+        index: 2,
+        siblings: siblings.into_iter().map(Value::known).collect(),
+    };
+
+    let siblings = merkle_path_native(&levels, 2);
+    assert!(verify_gadget(9, gadget(siblings.clone()), vec![root]));
+    assert!(!verify_gadget(9, gadget(siblings), vec![root + Fp::ONE]));
+
+    // The path of another leaf does not open index 2.
+    let siblings = merkle_path_native(&levels, 1);
+    assert!(!verify_gadget(9, gadget(siblings), vec![root]));
+}
+
+#[derive(Default)]
+struct LoadInstanceGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for LoadInstanceGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_instance(layouter.namespace(|| "load b"), 0)?;
+
+        let c = chip.add(layouter.namespace(|| "a + b"), a, b)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose c"), c, 1)?)
+    }
+}
+
+#[test]
+fn test_load_instance() {
+    let gadget = || LoadInstanceGadget {
+        a: Value::known(Fp::from(2)),
+    };
+    assert!(verify_gadget(6, gadget(), vec![Fp::from(3), Fp::from(5)]));
+    assert!(!verify_gadget(6, gadget(), vec![Fp::from(4), Fp::from(5)]));
+}
+
+#[derive(Default)]
+struct SumGadget {
+    xs: Vec<Value<Fp>>,
+    accumulated: bool,
+}
+
+impl Gadget for SumGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let xs =
+            chip.load_private_iter(layouter.namespace(|| "load xs"), self.xs.iter().copied())?;
+
+        let sum = if self.accumulated {
+            chip.sum_accumulated(layouter.namespace(|| "sum"), &xs)?
+        } else {
+            chip.sum(&mut layouter, &xs)?
+        };
+        Ok(chip.expose_public(layouter.namespace(|| "expose sum"), sum, 0)?)
+    }
+}
+
+/// Checks the rows used by chaining `add`s against the single-region accumulator.
+///
+/// Both start below the `n` rows of `load_private_iter`. Chaining takes one two-row `add`
+/// region per summand after the first, the accumulator a single region of `n` rows:
+///
+/// | n   | chained (3n - 2) | accumulated (2n) |
+/// |-----|------------------|------------------|
+/// | 16  | 46               | 32               |
+/// | 64  | 190              | 128              |
+/// | 256 | 766              | 512              |
+#[test]
+fn test_sum_row_cost() {
+    let k = 10;
+
+    for n in [16, 64, 256] {
+        let xs = (0..n as u64).map(Fp::from).collect::<Vec<_>>();
+        let sum = xs.iter().sum::<Fp>();
+
+        let rows = |accumulated| {
+            let circuit = GadgetCircuit::new(SumGadget {
+                xs: xs.iter().copied().map(Value::known).collect(),
+                accumulated,
+            });
+            let prover = MockProver::run(k, &circuit, vec![vec![sum]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            used_rows(&prover)
+        };
+
+        assert_eq!(rows(false), 3 * n - 2);
+        assert_eq!(rows(true), 2 * n);
+    }
+}
+
+#[derive(Default)]
+struct ExpandChallengesGadget {
+    seed: Value<Fp>,
+    n: usize,
+}
+
+impl Gadget for ExpandChallengesGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let seed = chip.load_private(layouter.namespace(|| "load seed"), self.seed)?;
+
+        let challenges = chip.expand_challenges(&mut layouter, seed, self.n)?;
+        for (i, challenge) in challenges.into_iter().enumerate() {
+            chip.expose_public(
+                layouter.namespace(|| format!("expose challenge_{i}")),
+                challenge,
+                i,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_expand_challenges() {
+    let seed = Fp::random(OsRng);
+    let n = 4;
+    let mut challenges = (0..n as u64)
+        .map(|i| poseidon_native([seed, Fp::from(i)]))
+        .collect::<Vec<_>>();
+
+    let gadget = || ExpandChallengesGadget {
+        seed: Value::known(seed),
+        n,
+    };
+    assert!(verify_gadget(9, gadget(), challenges.clone()));
+
+    challenges[n - 1] += Fp::ONE;
+    assert!(!verify_gadget(9, gadget(), challenges));
+}
+
+#[test]
+fn test_real_proofs_are_blinded() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let circuit = || MyCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+    let public_inputs = circuit().expected_public_inputs();
+
+    // Measure the rows the circuit uses at a comfortable size, then shrink to the smallest
+    // `k` that still leaves room for the blinding rows.
+    let prover = MockProver::run(10, &circuit(), vec![public_inputs.clone()]).unwrap();
+    let k = minimum_k::<MyCircuit<Fp>>(used_rows(&prover));
+    let prover = MockProver::run(k, &circuit(), vec![public_inputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let vk = keygen_vk(&params, &circuit()).unwrap();
+    let pk = keygen_pk(&params, vk, &circuit()).unwrap();
+
+    // Two proofs of the same statement with the same witness must still differ, since the
+    // prover blinds every advice polynomial with fresh randomness.
+    let first = create_real_proof(&params, &pk, circuit(), &public_inputs).unwrap();
+    let second = create_real_proof(&params, &pk, circuit(), &public_inputs).unwrap();
+    assert_ne!(first, second);
+
+    assert!(verify_real_proof(
+        &params,
+        pk.get_vk(),
+        &first,
+        &public_inputs
+    ));
+    assert!(verify_real_proof(
+        &params,
+        pk.get_vk(),
+        &second,
+        &public_inputs
+    ));
+}
+
+#[derive(Default)]
+struct CommitWithRangeGadget {
+    value: Value<Fp>,
+    blinding: Value<Fp>,
+}
+
+impl Gadget for CommitWithRangeGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let value = chip.load_private(layouter.namespace(|| "load value"), self.value)?;
+        let blinding = chip.load_private(layouter.namespace(|| "load blinding"), self.blinding)?;
+
+        let commitment = chip.commit_with_range(&mut layouter, value, blinding, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose commitment"), commitment, 0)?)
+    }
+}
+
+#[test]
+fn test_commit_with_range() {
+    let blinding = Fp::random(OsRng);
+    let gadget = |value: u64| CommitWithRangeGadget {
+        value: Value::known(Fp::from(value)),
+        blinding: Value::known(blinding),
+    };
+
+    let commitment = poseidon_native([Fp::from(200), blinding]);
+    assert!(verify_gadget(8, gadget(200), vec![commitment]));
+    assert!(!verify_gadget(8, gadget(200), vec![commitment + Fp::ONE]));
+
+    // 300 does not fit in 8 bits, so the circuit refuses to synthesize it.
+    assert!(matches!(
+        gadget_error(8, gadget(300)),
+        PlaygroundError::OutOfRange { n_bits: 8 }
+    ));
+}
+
+#[derive(Default)]
+struct ChallengeBitsGadget {
+    inputs: [Value<Fp>; 3],
+    n_bits: usize,
+}
+
+impl Gadget for ChallengeBitsGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let mut transcript = CircuitTranscript::new(chip, layouter.namespace(|| "transcript"))?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            let input =
+                chip.load_private(layouter.namespace(|| format!("load input_{i}")), *input)?;
+            transcript.absorb(
+                chip,
+                layouter.namespace(|| format!("absorb input_{i}")),
+                input,
+            )?;
+        }
+
+        let bits = chip.squeeze_challenge_bits(&mut layouter, &mut transcript, self.n_bits)?;
+        let low_bits = chip.from_bits(&mut layouter, &bits)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose low bits"), low_bits, 0)?)
+    }
+}
+
+#[test]
+fn test_squeeze_challenge_bits() {
+    // Three inputs overflow the rate, so the transcript permutes once while absorbing.
+    let inputs = [Fp::from(1), Fp::from(2), Fp::from(3)];
+    let n_bits = 8;
+
+    let mut sponge = TranscriptSponge::new();
+    for input in inputs {
+        sponge.absorb(input);
+    }
+    let challenge = sponge.squeeze().to_repr();
+    let low_bits = Fp::from(challenge[0] as u64);
+
+    let gadget = || ChallengeBitsGadget {
+        inputs: inputs.map(Value::known),
+        n_bits,
+    };
+    assert!(verify_gadget(11, gadget(), vec![low_bits]));
+    assert!(!verify_gadget(11, gadget(), vec![low_bits + Fp::ONE]));
+}
+
+/// Squeezes a challenge like `squeeze_challenge_bits`, but constrains it against the
+/// decomposition `bits` given by the test rather than the one the chip witnesses.
+#[derive(Default)]
+struct ChallengeDecompositionGadget {
+    inputs: [Value<Fp>; 3],
+    bits: Vec<Value<Fp>>,
+}
+
+impl Gadget for ChallengeDecompositionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let mut transcript = CircuitTranscript::new(chip, layouter.namespace(|| "transcript"))?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            let input =
+                chip.load_private(layouter.namespace(|| format!("load input_{i}")), *input)?;
+            transcript.absorb(
+                chip,
+                layouter.namespace(|| format!("absorb input_{i}")),
+                input,
+            )?;
+        }
+        let bits = chip.load_private_iter(
+            layouter.namespace(|| "load bits"),
+            self.bits.iter().copied(),
+        )?;
+
+        let challenge = transcript.squeeze(chip, layouter.namespace(|| "squeeze challenge"))?;
+        chip.assert_bits(&mut layouter, challenge, &bits)
+    }
+}
+
+#[test]
+fn test_squeeze_challenge_bits_canonical() {
+    // Draw inputs until the challenge `c` has a second decomposition, as the bits of `c + p`.
+    let (inputs, challenge, forged) = loop {
+        let inputs = [(); 3].map(|_| Fp::random(OsRng));
+        let mut sponge = TranscriptSponge::new();
+        for input in inputs {
+            sponge.absorb(input);
+        }
+        let challenge = sponge.squeeze();
+        if let Some(forged) = repr_plus_modulus(challenge) {
+            break (inputs, challenge, forged);
+        }
+    };
+
+    let gadget = |bits: Vec<Fp>| ChallengeDecompositionGadget {
+        inputs: inputs.map(Value::known),
+        bits: bits.into_iter().map(Value::known).collect(),
+    };
+    assert!(verify_gadget(
+        11,
+        gadget(repr_bits(challenge.to_repr())),
+        vec![]
+    ));
+    assert!(!verify_gadget(11, gadget(repr_bits(forged)), vec![]));
+}
+
+#[test]
+fn test_configure_columns() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = MyCircuit::<Fp>::configure(&mut meta);
+
+    // WIDTH advice columns plus Poseidon's partial S-box column, and two sets of round
+    // constants.
+    assert_eq!(meta.num_advice_columns(), WIDTH + 1);
+    assert_eq!(meta.num_fixed_columns(), 2 * WIDTH);
+    assert_eq!(meta.num_instance_columns(), 1);
+
+    assert_eq!(
+        config.column_stats(),
+        ColumnStats {
+            advice: 4,
+            fixed: 6,
+            instance: 1,
+            selectors: 10,
+        }
+    );
+    assert_eq!(config.column_stats().selectors, meta.num_selectors());
+}
+
+#[derive(Default)]
+struct PowVarGadget {
+    base: Value<Fp>,
+    exp: Value<Fp>,
+}
+
+impl Gadget for PowVarGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let base = chip.load_private(layouter.namespace(|| "load base"), self.base)?;
+        let exp = chip.load_private(layouter.namespace(|| "load exp"), self.exp)?;
+
+        let pow = chip.pow_var(&mut layouter, base, exp, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose pow"), pow, 0)?)
+    }
+}
+
+#[test]
+fn test_pow_var() {
+    let base = Fp::random(OsRng);
+    let gadget = |exp: u64| PowVarGadget {
+        base: Value::known(base),
+        exp: Value::known(Fp::from(exp)),
+    };
+
+    for exp in [0u64, 1, 2, 5, 128, 255] {
+        let pow = base.pow_vartime([exp]);
+        assert!(verify_gadget(8, gadget(exp), vec![pow]));
+        assert!(!verify_gadget(8, gadget(exp), vec![pow * base]));
+    }
+}
+
+#[derive(Default)]
+struct PrfGadget {
+    key: Value<Fp>,
+    input: Value<Fp>,
+}
+
+impl Gadget for PrfGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let key = chip.load_private(layouter.namespace(|| "load key"), self.key)?;
+        let input = chip.load_private(layouter.namespace(|| "load input"), self.input)?;
+
+        let output = chip.prf(&mut layouter, key, input)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose output"), output, 0)?)
+    }
+}
+
+#[test]
+fn test_prf() {
+    let key = Fp::random(OsRng);
+    let input = Fp::from(7);
+    let gadget = |key: Fp| PrfGadget {
+        key: Value::known(key),
+        input: Value::known(input),
+    };
+
+    let output = poseidon_native([Fp::from(PRF_TAG), key, input]);
+    assert!(verify_gadget(8, gadget(key), vec![output]));
+
+    // The output depends on the key, and differs from a plain hash of the same inputs.
+    assert!(!verify_gadget(8, gadget(key + Fp::ONE), vec![output]));
+    assert_ne!(output, poseidon_native([key, input]));
+}
+
+/// A deliberately tiny parameter set: two full and two partial rounds with made-up round
+/// constants, reusing the MDS matrix of `P128Pow5T3`.
+struct SmallTable;
+
+impl ConstantTable for SmallTable {
+    const FULL_ROUNDS: usize = 2;
+    const PARTIAL_ROUNDS: usize = 2;
+
+    fn round_constants() -> Vec<[Fp; WIDTH]> {
+        (0..4)
+            .map(|round| [0, 1, 2].map(|i| Fp::from((WIDTH * round + i + 1) as u64)))
+            .collect()
+    }
+
+    fn mds() -> [[Fp; WIDTH]; WIDTH] {
+        <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::constants().1
+    }
+
+    fn mds_inv() -> [[Fp; WIDTH]; WIDTH] {
+        <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::constants().2
+    }
+}
+
+/// `SmallTable` with its last row of round constants missing.
+struct ShortTable;
+
+impl ConstantTable for ShortTable {
+    const FULL_ROUNDS: usize = 2;
+    const PARTIAL_ROUNDS: usize = 2;
+
+    fn round_constants() -> Vec<[Fp; WIDTH]> {
+        let mut round_constants = SmallTable::round_constants();
+        round_constants.pop();
+        round_constants
+    }
+
+    fn mds() -> [[Fp; WIDTH]; WIDTH] {
+        SmallTable::mds()
+    }
+
+    fn mds_inv() -> [[Fp; WIDTH]; WIDTH] {
+        SmallTable::mds_inv()
+    }
+}
+
+/// `SmallTable` with a third partial round, which `Pow5Chip` cannot pair up.
+struct OddTable;
+
+impl ConstantTable for OddTable {
+    const FULL_ROUNDS: usize = 2;
+    const PARTIAL_ROUNDS: usize = 3;
+
+    fn round_constants() -> Vec<[Fp; WIDTH]> {
+        let mut round_constants = SmallTable::round_constants();
+        round_constants.push(round_constants[0]);
+        round_constants
+    }
+
+    fn mds() -> [[Fp; WIDTH]; WIDTH] {
+        SmallTable::mds()
+    }
+
+    fn mds_inv() -> [[Fp; WIDTH]; WIDTH] {
+        SmallTable::mds_inv()
+    }
+}
+
+#[derive(Default)]
+struct TableHashCircuit {
+    inputs: [Value<Fp>; 2],
+}
+
+impl Circuit<Fp> for TableHashCircuit {
+    type Config = (
+        Pow5Config<Fp, WIDTH, RATE>,
+        [Column<Advice>; WIDTH],
+        Column<Instance>,
+    );
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let state = [(); WIDTH].map(|_| meta.advice_column());
+        let partial_sbox = meta.advice_column();
+        let rc_a = [(); WIDTH].map(|_| meta.fixed_column());
+        let rc_b = [(); WIDTH].map(|_| meta.fixed_column());
+        meta.enable_constant(rc_b[0]);
+
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        let config =
+            configure_poseidon_with_table::<SmallTable>(meta, state, partial_sbox, rc_a, rc_b)
+                .unwrap();
+
+        (config, state, instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, state, instance): Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let inputs = layouter.assign_region(
+            || "load inputs",
+            |mut region| {
+                let x = region.assign_advice(|| "x", state[0], 0, || self.inputs[0])?;
+                let y = region.assign_advice(|| "y", state[1], 0, || self.inputs[1])?;
+                Ok([x, y])
+            },
+        )?;
+
+        let chip = Pow5Chip::construct(config);
+        let hasher =
+            PoseidonHash::<_, _, TableSpec<SmallTable>, ConstantLength<2>, WIDTH, RATE>::init(
+                chip,
+                layouter.namespace(|| "init hasher"),
+            )?;
+        let digest = hasher.hash(layouter.namespace(|| "hash"), inputs)?;
+
+        layouter.constrain_instance(digest.cell(), instance, 0)
+    }
+}
+
+#[test]
+fn test_custom_round_constants() {
+    let inputs = [Fp::from(1), Fp::from(2)];
+    let digest = poseidon::Hash::<_, TableSpec<SmallTable>, ConstantLength<2>, WIDTH, RATE>::init()
+        .hash(inputs);
+    assert_ne!(digest, poseidon_native(inputs));
+
+    let circuit = TableHashCircuit {
+        inputs: inputs.map(Value::known),
+    };
+    let prover = MockProver::run(6, &circuit, vec![vec![digest]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(6, &circuit, vec![vec![poseidon_native(inputs)]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let state = [(); WIDTH].map(|_| meta.advice_column());
+    let partial_sbox = meta.advice_column();
+    let rc_a = [(); WIDTH].map(|_| meta.fixed_column());
+    let rc_b = [(); WIDTH].map(|_| meta.fixed_column());
+    assert!(matches!(
+        configure_poseidon_with_table::<ShortTable>(&mut meta, state, partial_sbox, rc_a, rc_b),
+        Err(PlaygroundError::LengthMismatch {
+            expected: 4,
+            actual: 3
+        })
+    ));
+    assert!(matches!(
+        configure_poseidon_with_table::<OddTable>(&mut meta, state, partial_sbox, rc_a, rc_b),
+        Err(PlaygroundError::OddRounds {
+            full_rounds: 2,
+            partial_rounds: 3
+        })
+    ));
+}
+
+/// Forks `sponge`, then checks that feeding both forks `suffix` yields the same
+/// challenges, while changing the last element of `suffix` in one of them makes them
+/// diverge.
+fn assert_transcript_forks(mut sponge: TranscriptSponge, suffix: &[Fp]) {
+    let mut same = sponge.clone();
+    let mut different = sponge.clone();
+    let mut tampered = suffix.to_vec();
+    *tampered.last_mut().unwrap() += Fp::ONE;
+
+    for (input, tampered) in suffix.iter().zip(&tampered) {
+        sponge.absorb(*input);
+        same.absorb(*input);
+        different.absorb(*tampered);
+    }
+
+    let challenge = sponge.squeeze();
+    assert_eq!(challenge, same.squeeze());
+    assert_ne!(challenge, different.squeeze());
+
+    // Squeezing again drains the rest of the rate, then forces a fresh permutation.
+    for _ in 0..RATE {
+        let challenge = sponge.squeeze();
+        assert_eq!(challenge, same.squeeze());
+        assert_ne!(challenge, different.squeeze());
+    }
+}
+
+#[test]
+fn test_transcript_fork() {
+    // A suffix overflowing the rate buffer after a partially filled one.
+    let mut sponge = TranscriptSponge::new();
+    sponge.absorb(Fp::from(1));
+    assert_transcript_forks(sponge.clone(), &[Fp::from(2), Fp::from(3), Fp::from(4)]);
+
+    // Absorbing right after a squeeze starts a new rate buffer with `init_with`.
+    let first = sponge.squeeze();
+    assert_transcript_forks(sponge.clone(), &[first, Fp::from(5)]);
+
+    // The squeeze is part of the history: skipping it changes later challenges.
+    let mut unsqueezed = TranscriptSponge::new();
+    unsqueezed.absorb(Fp::from(1));
+    unsqueezed.absorb(first);
+    sponge.absorb(first);
+    assert_ne!(unsqueezed.squeeze(), sponge.squeeze());
+}
+
+#[derive(Default)]
+struct FermatInverseGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for FermatInverseGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let one = chip.load_constant(layouter.namespace(|| "load one"), Fp::ONE)?;
+
+        let fermat = chip.inverse_by_fermat(&mut layouter, a.clone())?;
+        let witnessed = chip.div(&mut layouter, one, a)?;
+        chip.expose_public(layouter.namespace(|| "expose fermat"), fermat, 0)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose witnessed"), witnessed, 1)?)
+    }
+}
+
+#[test]
+fn test_inverse_by_fermat() {
+    let a = Fp::random(OsRng);
+    let a_inv = a.invert().unwrap();
+    let gadget = || FermatInverseGadget { a: Value::known(a) };
+
+    assert!(verify_gadget(10, gadget(), vec![a_inv, a_inv]));
+    assert!(!verify_gadget(10, gadget(), vec![a_inv + Fp::ONE, a_inv]));
+}
+
+#[derive(Default)]
+struct DiffOfSquaresGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for DiffOfSquaresGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let diff = chip.diff_of_squares(&mut layouter, a.clone(), b.clone())?;
+
+        let a_squared = chip.mul(layouter.namespace(|| "a * a"), a.clone(), a)?;
+        let b_squared = chip.mul(layouter.namespace(|| "b * b"), b.clone(), b)?;
+        let expected = chip.sub(layouter.namespace(|| "a^2 - b^2"), a_squared, b_squared)?;
+        chip.assert_equal(
+            layouter.namespace(|| "diff = expected"),
+            diff.clone(),
+            expected,
+        )?;
+
+        Ok(chip.expose_public(layouter.namespace(|| "expose diff"), diff, 0)?)
+    }
+}
+
+#[test]
+fn test_diff_of_squares() {
+    let [a, b] = [(); 2].map(|_| Fp::random(OsRng));
+    let gadget = || DiffOfSquaresGadget {
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+
+    assert!(verify_gadget(5, gadget(), vec![a.square() - b.square()]));
+    assert!(!verify_gadget(5, gadget(), vec![a.square() + b.square()]));
+}
+
+/// Hashes four private inputs with a `FieldChip` of width `W` and exposes the digest.
+#[derive(Default)]
+struct WidthCircuit<const W: usize, const R: usize> {
+    inputs: [Value<Fp>; 4],
+}
+
+impl<const W: usize, const R: usize> Circuit<Fp> for WidthCircuit<W, R>
+where
+    FieldChip<Fp, W, R>: PoseidonSpec<W, R>,
+{
+    type Config = FieldConfig<Fp, W, R>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+        FieldChip::<Fp, W, R>::configure_with_fresh_columns(meta, instance)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, W, R>::construct(config, ());
+
+        let mut inputs = Vec::with_capacity(4);
+        for (i, input) in self.inputs.iter().enumerate() {
+            inputs
+                .push(chip.load_private(layouter.namespace(|| format!("load input_{i}")), *input)?);
+        }
+
+        let digest =
+            chip.poseidon_hash(layouter.namespace(|| "hash"), inputs.try_into().unwrap())?;
+        chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)
+    }
+}
+
+/// Checks the width-`W` chip against the reference hasher for its spec.
+fn check_poseidon_width<const W: usize, const R: usize>()
+where
+    FieldChip<Fp, W, R>: PoseidonSpec<W, R>,
+{
+    let inputs = [(); 4].map(|_| Fp::random(OsRng));
+    let digest = poseidon::Hash::<
+        _,
+        <FieldChip<Fp, W, R> as PoseidonSpec<W, R>>::Spec,
+        ConstantLength<4>,
+        W,
+        R,
+    >::init()
+    .hash(inputs);
+
+    let circuit = WidthCircuit::<W, R> {
+        inputs: inputs.map(Value::known),
+    };
+    let prover = MockProver::run(8, &circuit, vec![vec![digest]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(8, &circuit, vec![vec![digest + Fp::ONE]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn test_poseidon_widths() {
+    check_poseidon_width::<3, 2>();
+    check_poseidon_width::<5, 4>();
+}
+
+#[test]
+fn test_expected_public_inputs() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let circuit = MyCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+
+    let public_inputs = circuit.expected_public_inputs();
+    assert_eq!(public_inputs.len(), 2);
+
+    let prover = MockProver::run(6, &circuit, vec![public_inputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    assert!(assert_mock_and_real_agree(circuit, 6, &public_inputs));
+}
+
+#[derive(Default)]
+struct InverseGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for InverseGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+
+        chip.assert_nonzero(&mut layouter, a.clone())?;
+        let a_inv = chip.inv_or_error(&mut layouter, a)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose a^-1"), a_inv, 0)?)
+    }
+}
+
+#[test]
+fn test_inv_or_error() {
+    let a = Fp::random(OsRng);
+    let a_inv = a.invert().unwrap();
+    let gadget = |a: Fp| InverseGadget { a: Value::known(a) };
+
+    assert!(verify_gadget(5, gadget(a), vec![a_inv]));
+    assert!(!verify_gadget(5, gadget(a), vec![a_inv + Fp::ONE]));
+
+    // Zero has no inverse, so the circuit refuses to synthesize it.
+    assert!(matches!(
+        gadget_error(5, gadget(Fp::ZERO)),
+        PlaygroundError::DivisionByZero
+    ));
+}
+
+#[derive(Default)]
+struct InnerProductGadget {
+    inputs: [Value<Fp>; 4],
+}
+
+impl Gadget for InnerProductGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|i| {
+            chip.load_private(
+                layouter.namespace(|| format!("load input_{i}")),
+                self.inputs[i],
+            )
+        });
+        let [a, b, c, d] = [a?, b?, c?, d?];
+
+        let inner_product = chip.inner_product2(
+            layouter.namespace(|| "a * b + c * d"),
+            a.clone(),
+            b.clone(),
+            c.clone(),
+            d.clone(),
+        )?;
+
+        let ab = chip.mul(layouter.namespace(|| "a * b"), a, b)?;
+        let cd = chip.mul(layouter.namespace(|| "c * d"), c, d)?;
+        let expected = chip.add(layouter.namespace(|| "ab + cd"), ab, cd)?;
+        chip.assert_equal(
+            layouter.namespace(|| "inner product = expected"),
+            inner_product.clone(),
+            expected,
+        )?;
+
+        Ok(chip.expose_public(
+            layouter.namespace(|| "expose inner product"),
+            inner_product,
+            0,
+        )?)
+    }
+}
+
+#[test]
+fn test_inner_product2() {
+    let [a, b, c, d] = [(); 4].map(|_| Fp::random(OsRng));
+    let gadget = || InnerProductGadget {
+        inputs: [a, b, c, d].map(Value::known),
+    };
+
+    assert!(verify_gadget(6, gadget(), vec![a * b + c * d]));
+    assert!(!verify_gadget(6, gadget(), vec![a * b - c * d]));
+}
+
+#[derive(Default)]
+struct ComplexMulGadget {
+    a: (Value<Fp>, Value<Fp>),
+    b: (Value<Fp>, Value<Fp>),
+}
+
+impl Gadget for ComplexMulGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let ar = chip.load_private(layouter.namespace(|| "load ar"), self.a.0)?;
+        let ai = chip.load_private(layouter.namespace(|| "load ai"), self.a.1)?;
+        let br = chip.load_private(layouter.namespace(|| "load br"), self.b.0)?;
+        let bi = chip.load_private(layouter.namespace(|| "load bi"), self.b.1)?;
+
+        let (real, imaginary) = chip.complex_mul(&mut layouter, (ar, ai), (br, bi))?;
+        chip.expose_public(layouter.namespace(|| "expose real"), real, 0)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose imaginary"), imaginary, 1)?)
+    }
+}
+
+#[test]
+fn test_complex_mul() {
+    let [ar, ai, br, bi] = [(); 4].map(|_| Fp::random(OsRng));
+    let gadget = || ComplexMulGadget {
+        a: (Value::known(ar), Value::known(ai)),
+        b: (Value::known(br), Value::known(bi)),
+    };
+
+    let product = vec![ar * br - ai * bi, ar * bi + ai * br];
+    assert!(verify_gadget(6, gadget(), product.clone()));
+
+    // Swapping the operands gives the same product, as multiplication is commutative.
+    let swapped = ComplexMulGadget {
+        a: (Value::known(br), Value::known(bi)),
+        b: (Value::known(ar), Value::known(ai)),
+    };
+    assert!(verify_gadget(6, swapped, product));
+
+    // (ar + ai * i) * i = -ai + ar * i.
+    let times_i = ComplexMulGadget {
+        a: (Value::known(ar), Value::known(ai)),
+        b: (Value::known(Fp::ZERO), Value::known(Fp::ONE)),
+    };
+    assert!(verify_gadget(6, times_i, vec![-ai, ar]));
+
+    assert!(!verify_gadget(
+        6,
+        gadget(),
+        vec![ar * br + ai * bi, ar * bi + ai * br]
+    ));
+}
+
+#[derive(Default)]
+struct OrGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    check_inputs: bool,
+}
+
+impl Gadget for OrGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let or = chip.or(layouter.namespace(|| "a or b"), a, b, self.check_inputs)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose or"), or, 0)?)
+    }
+}
+
+#[test]
+fn test_or() {
+    let gadget = |a: u64, b: u64, check_inputs| OrGadget {
+        a: Value::known(Fp::from(a)),
+        b: Value::known(Fp::from(b)),
+        check_inputs,
+    };
+
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let or = Fp::from(a | b);
+        assert!(verify_gadget(5, gadget(a, b, true), vec![or]));
+        assert!(!verify_gadget(5, gadget(a, b, true), vec![Fp::ONE - or]));
+    }
+
+    // 2 OR 0 evaluates to 2 and only the boolean check rejects it.
+    assert!(verify_gadget(5, gadget(2, 0, false), vec![Fp::from(2)]));
+    assert!(!verify_gadget(5, gadget(2, 0, true), vec![Fp::from(2)]));
+}
+
+#[derive(Default)]
+struct NotGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for NotGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+
+        let not = chip.not(&mut layouter, a)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose not"), not, 0)?)
+    }
+}
+
+#[test]
+fn test_not() {
+    let gadget = |a: u64| NotGadget {
+        a: Value::known(Fp::from(a)),
+    };
+
+    assert!(verify_gadget(5, gadget(0), vec![Fp::ONE]));
+    assert!(verify_gadget(5, gadget(1), vec![Fp::ZERO]));
+    assert!(!verify_gadget(5, gadget(1), vec![Fp::ONE]));
+
+    // 1 - 2 would be -1, but 2 is not boolean.
+    assert!(!verify_gadget(5, gadget(2), vec![-Fp::ONE]));
+}
+
+#[derive(Default)]
+struct BindWitnessGadget {
+    witness: [Value<Fp>; 3],
+}
+
+impl Gadget for BindWitnessGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let [x, y, z] = [0, 1, 2].map(|i| {
+            chip.load_private(
+                layouter.namespace(|| format!("load witness_{i}")),
+                self.witness[i],
+            )
+        });
+
+        chip.bind_witness(&mut layouter, [x?, y?, z?], 0)
+    }
+}
+
+#[test]
+fn test_bind_witness() {
+    let witness = [(); 3].map(|_| Fp::random(OsRng));
+    let gadget = |witness: [Fp; 3]| BindWitnessGadget {
+        witness: witness.map(Value::known),
+    };
+
+    let commitment = poseidon_native(witness);
+    assert!(verify_gadget(7, gadget(witness), vec![commitment]));
+
+    let mut other = witness;
+    other[2] += Fp::ONE;
+    assert!(!verify_gadget(7, gadget(other), vec![commitment]));
+}
+
+#[derive(Default)]
+struct NamedLoadGadget {
+    secret: Value<Fp>,
+}
+
+impl Gadget for NamedLoadGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let secret =
+            chip.load_private_named(layouter.namespace(|| "load secret"), "secret", self.secret)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose secret"), secret, 0)?)
+    }
+}
+
+#[test]
+fn test_load_private_named() {
+    let secret = Fp::random(OsRng);
+    let circuit = GadgetCircuit::new(NamedLoadGadget {
+        secret: Value::known(secret),
+    });
+
+    let prover = MockProver::run(5, &circuit, vec![vec![secret]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The mismatching copy is reported in the region named after the input.
+    let prover = MockProver::run(5, &circuit, vec![vec![secret + Fp::ONE]]).unwrap();
+    let failures = prover.verify().unwrap_err();
+    assert!(format!("{failures:?}").contains("secret"));
+}
+
+#[derive(Default)]
+struct CheckedSubGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for CheckedSubGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let diff = chip.checked_sub(&mut layouter, a, b, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose diff"), diff, 0)?)
+    }
+}
+
+#[test]
+fn test_checked_sub() {
+    let gadget = |a: u64, b: u64| CheckedSubGadget {
+        a: Value::known(Fp::from(a)),
+        b: Value::known(Fp::from(b)),
+    };
+
+    assert!(verify_gadget(7, gadget(200, 50), vec![Fp::from(150)]));
+    assert!(!verify_gadget(7, gadget(200, 50), vec![Fp::from(151)]));
+
+    // 50 - 200 wraps around the modulus, so the circuit refuses to synthesize it.
+    assert!(matches!(
+        gadget_error(7, gadget(50, 200)),
+        PlaygroundError::OutOfRange { n_bits: 8 }
+    ));
+}
+
+#[derive(Default)]
+struct HashToGadget {
+    inputs: [Value<Fp>; 2],
+}
+
+impl Gadget for HashToGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.inputs[0])?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.inputs[1])?;
+
+        let outputs = chip.poseidon_hash_to(layouter.namespace(|| "hash"), [x, y], 2)?;
+        for (i, output) in outputs.into_iter().enumerate() {
+            chip.expose_public(
+                layouter.namespace(|| format!("expose output_{i}")),
+                output,
+                i,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_poseidon_hash_to() {
+    let inputs = [(); 2].map(|_| Fp::random(OsRng));
+
+    // Two inputs exactly fill the rate, so there is no padding to absorb.
+    let mut sponge = TranscriptSponge::with_capacity(
+        <ConstantLength<2> as Domain<Fp, RATE>>::initial_capacity_element(),
+    );
+    for input in inputs {
+        sponge.absorb(input);
+    }
+    let outputs = vec![sponge.squeeze(), sponge.squeeze()];
+    assert_eq!(outputs[0], poseidon_native(inputs));
+
+    let gadget = || HashToGadget {
+        inputs: inputs.map(Value::known),
+    };
+    assert!(verify_gadget(7, gadget(), outputs.clone()));
+    assert!(!verify_gadget(7, gadget(), vec![outputs[0], outputs[0]]));
+}
+
+#[derive(Default)]
+struct AllDistinctGadget {
+    xs: Vec<Value<Fp>>,
+}
+
+impl Gadget for AllDistinctGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let xs = self
+            .xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| chip.load_private(layouter.namespace(|| format!("load x_{i}")), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        chip.assert_all_distinct(&mut layouter, &xs)
+    }
+}
+
+#[test]
+fn test_assert_all_distinct() {
+    let gadget = |xs: &[u64]| AllDistinctGadget {
+        xs: xs.iter().map(|x| Value::known(Fp::from(*x))).collect(),
+    };
+
+    assert!(verify_gadget(7, gadget(&[1, 2, 3, 4]), vec![]));
+    assert!(matches!(
+        gadget_error(7, gadget(&[1, 2, 3, 2])),
+        PlaygroundError::DuplicateInput
+    ));
+}
+
+#[test]
+fn test_op_circuit() {
+    let [a, b] = [(); 2].map(|_| Fp::random(OsRng));
+    let circuit = OpCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+
+    let run = |public_inputs: Vec<Fp>| {
+        MockProver::run(5, &circuit, vec![public_inputs])
+            .unwrap()
+            .verify()
+            .is_ok()
+    };
+
+    assert!(run(vec![Fp::ZERO, a + b]));
+    assert!(run(vec![Fp::ONE, a * b]));
+
+    // Each mode only accepts its own result.
+    assert!(!run(vec![Fp::ZERO, a * b]));
+    assert!(!run(vec![Fp::ONE, a + b]));
+
+    // A non-boolean op would blend the results, so it is rejected.
+    let blended = (a + b) + Fp::from(2) * (a * b - (a + b));
+    assert!(!run(vec![Fp::from(2), blended]));
+}
+
+#[derive(Default)]
+struct InnerProductNGadget {
+    a: Vec<Value<Fp>>,
+    b: Vec<Value<Fp>>,
+    accumulated: bool,
+}
+
+impl Gadget for InnerProductNGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private_iter(layouter.namespace(|| "load a"), self.a.iter().copied())?;
+        let b = chip.load_private_iter(layouter.namespace(|| "load b"), self.b.iter().copied())?;
+
+        let inner_product = if self.accumulated {
+            chip.inner_product(layouter.namespace(|| "inner product"), &a, &b)?
+        } else {
+            let products = a
+                .into_iter()
+                .zip(b)
+                .enumerate()
+                .map(|(i, (a_i, b_i))| {
+                    chip.mul(layouter.namespace(|| format!("a_{i} * b_{i}")), a_i, b_i)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            chip.sum(&mut layouter, &products)?
+        };
+        Ok(chip.expose_public(
+            layouter.namespace(|| "expose inner product"),
+            inner_product,
+            0,
+        )?)
+    }
+}
+
+#[test]
+fn test_inner_product() {
+    let n = 32;
+    let a = (0..n).map(|_| Fp::random(OsRng)).collect::<Vec<_>>();
+    let b = (0..n).map(|_| Fp::random(OsRng)).collect::<Vec<_>>();
+    let inner_product = a.iter().zip(&b).map(|(a_i, b_i)| *a_i * b_i).sum::<Fp>();
+
+    let rows = |accumulated, expected| {
+        let circuit = GadgetCircuit::new(InnerProductNGadget {
+            a: a.iter().copied().map(Value::known).collect(),
+            b: b.iter().copied().map(Value::known).collect(),
+            accumulated,
+        });
+        let prover = MockProver::run(9, &circuit, vec![vec![expected]]).unwrap();
+        (prover.verify().is_ok(), used_rows(&prover))
+    };
+
+    let (naive_ok, naive_rows) = rows(false, inner_product);
+    let (accumulated_ok, accumulated_rows) = rows(true, inner_product);
+    assert!(naive_ok && accumulated_ok);
+    assert!(accumulated_rows < naive_rows);
+
+    assert!(!rows(true, inner_product + Fp::ONE).0);
+}
+
+#[test]
+fn test_circuit_inputs() {
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::random(OsRng),
+        c: Fp::random(OsRng),
+    };
+
+    let circuit = MyCircuit::from(inputs);
+    assert_eq!(circuit.inputs(), Some(inputs));
+    assert_eq!(MyCircuit::<Fp>::default().inputs(), None);
+
+    let prover = MockProver::run(6, &circuit, vec![circuit.expected_public_inputs()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_circuit_inputs_serde() {
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::ONE,
+        c: -Fp::ONE,
+    };
+
+    let json = serde_json::to_string(&inputs).unwrap();
+    assert_eq!(
+        serde_json::from_str::<CircuitInputs>(&json).unwrap(),
+        inputs
+    );
+
+    // One is 0x01 followed by 31 zero bytes, little-endian.
+    assert!(json.contains(&format!("\"b\":\"01{}\"", "0".repeat(62))));
+
+    // The modulus itself, one more than -1, is not a canonical encoding.
+    let mut modulus = (-Fp::ONE).to_repr();
+    modulus[0] += 1;
+    let hex = modulus
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let json = format!(r#"{{"a":"{hex}","b":"{hex}","c":"{hex}"}}"#);
+    assert!(serde_json::from_str::<CircuitInputs>(&json).is_err());
+}
+
+#[derive(Default)]
+struct HashChainGadget {
+    start: Value<Fp>,
+    items: Vec<Value<Fp>>,
+}
+
+impl Gadget for HashChainGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let start = chip.load_private(layouter.namespace(|| "load start"), self.start)?;
+        let items = chip.load_private_iter(
+            layouter.namespace(|| "load items"),
+            self.items.iter().copied(),
+        )?;
+        let end = chip.load_instance(layouter.namespace(|| "load end"), 0)?;
+
+        chip.verify_hash_chain(&mut layouter, start, &items, end)
+    }
+}
+
+#[test]
+fn test_verify_hash_chain() {
+    let start = Fp::random(OsRng);
+    let items = [(); 3].map(|_| Fp::random(OsRng));
+    let end = items
+        .iter()
+        .fold(start, |h, item| poseidon_native([h, *item]));
+
+    let gadget = |items: [Fp; 3]| HashChainGadget {
+        start: Value::known(start),
+        items: items.map(Value::known).to_vec(),
+    };
+    assert!(verify_gadget(8, gadget(items), vec![end]));
+
+    let mut tampered = items;
+    tampered[1] += Fp::ONE;
+    assert!(!verify_gadget(8, gadget(tampered), vec![end]));
+}
+
+#[test]
+fn test_committed_circuit() {
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::random(OsRng),
+        c: Fp::random(OsRng),
+    };
+    let circuit = || CommittedCircuit::from(inputs);
+
+    let mut public_inputs = circuit().expected_public_inputs();
+    assert_eq!(public_inputs[0], (inputs.a + inputs.b) * inputs.c);
+    assert_eq!(
+        public_inputs[1],
+        poseidon_native([inputs.a, inputs.b, inputs.c])
+    );
+    assert!(assert_mock_and_real_agree(circuit(), 7, &public_inputs));
+
+    public_inputs[1] += Fp::ONE;
+    assert!(!assert_mock_and_real_agree(circuit(), 7, &public_inputs));
+}
+
+#[derive(Default)]
+struct CommitmentOfBitsGadget {
+    bits: Vec<Value<Fp>>,
+}
+
+impl Gadget for CommitmentOfBitsGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let bits = chip.load_private_iter(
+            layouter.namespace(|| "load bits"),
+            self.bits.iter().copied(),
+        )?;
+        let value = chip.load_instance(layouter.namespace(|| "load value"), 0)?;
+
+        chip.assert_commitment_of_bits(&mut layouter, value, &bits)
+    }
+}
+
+#[test]
+fn test_assert_commitment_of_bits() {
+    // 0b1011_0010 = 178, little-endian.
+    let bits = [0u64, 1, 0, 0, 1, 1, 0, 1];
+    let gadget = || CommitmentOfBitsGadget {
+        bits: bits
+            .iter()
+            .map(|bit| Value::known(Fp::from(*bit)))
+            .collect(),
+    };
+
+    let digest = poseidon_native([Fp::from(178)]);
+    assert!(verify_gadget(7, gadget(), vec![digest]));
+    assert!(!verify_gadget(
+        7,
+        gadget(),
+        vec![poseidon_native([Fp::from(179)])]
+    ));
+}
+
+#[derive(Default)]
+struct AssertMulGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for AssertMulGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let c = chip.load_instance(layouter.namespace(|| "load c"), 0)?;
+
+        Ok(chip.assert_mul(layouter.namespace(|| "a * b = c"), a, b, c)?)
+    }
+}
+
+#[test]
+fn test_assert_mul() {
+    let [a, b] = [(); 2].map(|_| Fp::random(OsRng));
+    let gadget = || AssertMulGadget {
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+
+    assert!(verify_gadget(5, gadget(), vec![a * b]));
+    assert!(!verify_gadget(5, gadget(), vec![a * b + Fp::ONE]));
+}
+
+#[derive(Default)]
+struct TranscriptGadget {
+    scalars: [Value<Fp>; 2],
+    response: Value<Fp>,
+}
+
+impl Gadget for TranscriptGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let scalars = chip.load_private_iter(
+            layouter.namespace(|| "load scalars"),
+            self.scalars.iter().copied(),
+        )?;
+        let response = chip.load_private(layouter.namespace(|| "load response"), self.response)?;
+
+        let mut transcript = Transcript::new(chip, layouter.namespace(|| "transcript"))?;
+        transcript.append_scalars(&mut layouter, &scalars)?;
+        let first = transcript.challenge(layouter.namespace(|| "first challenge"))?;
+
+        // A second round, absorbing after the first squeeze.
+        transcript.append_scalar(layouter.namespace(|| "append response"), response)?;
+        let second = transcript.challenge(layouter.namespace(|| "second challenge"))?;
+
+        chip.expose_public(layouter.namespace(|| "expose first"), first, 0)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose second"), second, 1)?)
+    }
+}
+
+#[test]
+fn test_transcript() {
+    let scalars = [(); 2].map(|_| Fp::random(OsRng));
+    let response = Fp::random(OsRng);
+
+    let mut sponge = TranscriptSponge::new();
+    for scalar in scalars {
+        sponge.absorb(scalar);
+    }
+    let first = sponge.squeeze();
+    sponge.absorb(response);
+    let second = sponge.squeeze();
+
+    let gadget = || TranscriptGadget {
+        scalars: scalars.map(Value::known),
+        response: Value::known(response),
+    };
+    assert!(verify_gadget(8, gadget(), vec![first, second]));
+    assert!(!verify_gadget(8, gadget(), vec![first, first]));
+}
+
+#[derive(Default)]
+struct WrapCellGadget {
+    inputs: [Value<Fp>; 2],
+    offset: Value<Fp>,
+}
+
+impl Gadget for WrapCellGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let inputs = chip.load_private_iter(
+            layouter.namespace(|| "load inputs"),
+            self.inputs.iter().copied(),
+        )?;
+        let offset = chip.load_private(layouter.namespace(|| "load offset"), self.offset)?;
+
+        // Hash with the `halo2_gadgets` API directly, which deals in `AssignedCell`s.
+        let pow5_chip = Pow5Chip::construct(chip.config.sponge_config()?);
+        let hasher = PoseidonHash::<_, _, P128Pow5T3, ConstantLength<2>, WIDTH, RATE>::init(
+            pow5_chip,
+            layouter.namespace(|| "init hasher"),
+        )?;
+        let inputs = [inputs[0].clone().into(), inputs[1].clone().into()];
+        let digest = hasher.hash(layouter.namespace(|| "hash"), inputs)?;
+
+        let sum = chip.add(layouter.namespace(|| "add"), digest.into(), offset)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose sum"), sum, 0)?)
+    }
+}
+
+#[test]
+fn test_number_from_assigned_cell() {
+    let inputs = [Fp::from(3), Fp::from(4)];
+    let offset = Fp::from(5);
+    let gadget = || WrapCellGadget {
+        inputs: inputs.map(Value::known),
+        offset: Value::known(offset),
+    };
+
+    let expected = poseidon_native(inputs) + offset;
+    assert!(verify_gadget(7, gadget(), vec![expected]));
+    assert!(!verify_gadget(7, gadget(), vec![poseidon_native(inputs)]));
+}
+
+#[derive(Default)]
+struct MembershipGadget {
+    leaf: Value<Fp>,
+    siblings: [Value<Fp>; 2],
+    is_right: [Value<Fp>; 2],
+    root: Value<Fp>,
+    secret: Value<Fp>,
+}
+
+impl Gadget for MembershipGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let leaf = chip.load_private(layouter.namespace(|| "load leaf"), self.leaf)?;
+        let siblings = chip.load_private_iter(
+            layouter.namespace(|| "load siblings"),
+            self.siblings.iter().copied(),
+        )?;
+        let is_right = chip.load_private_iter(
+            layouter.namespace(|| "load directions"),
+            self.is_right.iter().copied(),
+        )?;
+        let root = chip.load_private(layouter.namespace(|| "load root"), self.root)?;
+        let secret = chip.load_private(layouter.namespace(|| "load secret"), self.secret)?;
+
+        let path: Vec<_> = siblings.into_iter().zip(is_right).collect();
+        let nullifier =
+            chip.verify_membership_and_nullify(&mut layouter, leaf, &path, root, secret)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose nullifier"), nullifier, 0)?)
+    }
+}
+
+#[test]
+fn test_verify_membership_and_nullify() {
+    let leaves = [1, 2, 3, 4].map(Fp::from);
+    let left = poseidon_native([leaves[0], leaves[1]]);
+    let right = poseidon_native([leaves[2], leaves[3]]);
+    let root = poseidon_native([left, right]);
+    let secret = Fp::from(42);
+
+    // Leaf 2 is the left child of `right`, which is itself a right child.
+    let gadget = |root: Fp| MembershipGadget {
+        leaf: Value::known(leaves[2]),
+        siblings: [leaves[3], left].map(Value::known),
+        is_right: [Fp::ZERO, Fp::ONE].map(Value::known),
+        root: Value::known(root),
+        secret: Value::known(secret),
+    };
+
+    let nullifier = poseidon_native([secret, leaves[2]]);
+    assert!(verify_gadget(8, gadget(root), vec![nullifier]));
+    assert!(!verify_gadget(
+        8,
+        gadget(root),
+        vec![poseidon_native([secret, leaves[3]])]
+    ));
+    assert!(!verify_gadget(8, gadget(left), vec![nullifier]));
+}
+
+#[derive(Default)]
+struct PackedLoadGadget {
+    inputs: [Value<Fp>; 3],
+    packed: bool,
+}
+
+impl Gadget for PackedLoadGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let names = ["a", "b", "c"];
+        let [a, b, c]: [Number<Fp>; 3] = if self.packed {
+            let inputs: Vec<_> = names.into_iter().zip(self.inputs).collect();
+            chip.load_private_packed(layouter.namespace(|| "load inputs"), &inputs)?
+                .try_into()
+                .unwrap()
+        } else {
+            let mut loaded = Vec::new();
+            for (name, value) in names.into_iter().zip(self.inputs) {
+                loaded.push(chip.load_private_named(
+                    layouter.namespace(|| format!("load {name}")),
+                    name,
+                    value,
+                )?);
+            }
+            loaded.try_into().unwrap()
+        };
+
+        let d = chip.add_and_mul(&mut layouter, a, b, c)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose d"), d, 0)?)
+    }
+}
+
+#[test]
+fn test_load_private_packed() {
+    let inputs = [Fp::from(2), Fp::from(3), Fp::from(4)];
+    let d = (inputs[0] + inputs[1]) * inputs[2];
+
+    let rows = |packed, expected| {
+        let circuit = GadgetCircuit::new(PackedLoadGadget {
+            inputs: inputs.map(Value::known),
+            packed,
+        });
+        let prover = MockProver::run(5, &circuit, vec![vec![expected]]).unwrap();
+        (prover.verify().is_ok(), used_rows(&prover))
+    };
+
+    let (separate_ok, separate_rows) = rows(false, d);
+    let (packed_ok, packed_rows) = rows(true, d);
+    assert!(separate_ok && packed_ok);
+    assert_eq!(packed_rows + 2, separate_rows);
+
+    assert!(!rows(true, d + Fp::ONE).0);
+}
+
+#[derive(Default)]
+struct SquaredDistanceGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for SquaredDistanceGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let distance = chip.squared_distance(&mut layouter, a, b)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose distance"), distance, 0)?)
+    }
+}
+
+#[test]
+fn test_squared_distance() {
+    let [a, b] = [(); 2].map(|_| Fp::random(OsRng));
+    let gadget = || SquaredDistanceGadget {
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+
+    let expected = (a - b) * (a - b);
+    assert!(verify_gadget(5, gadget(), vec![expected]));
+    assert!(!verify_gadget(5, gadget(), vec![a.square() - b.square()]));
+}
+
+#[derive(Default)]
+struct EncryptionGadget {
+    plaintext: [Value<Fp>; 3],
+    key: Value<Fp>,
+}
+
+impl Gadget for EncryptionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let plaintext = chip.load_private_iter(
+            layouter.namespace(|| "load plaintext"),
+            self.plaintext.iter().copied(),
+        )?;
+        let key = chip.load_private(layouter.namespace(|| "load key"), self.key)?;
+
+        let ciphertext = chip.poseidon_encrypt(&mut layouter, &plaintext, key.clone())?;
+        let decrypted = chip.poseidon_decrypt(&mut layouter, &ciphertext, key)?;
+
+        for (i, (p, d)) in plaintext.into_iter().zip(decrypted).enumerate() {
+            chip.assert_equal(layouter.namespace(|| format!("round trip {i}")), p, d)?;
+        }
+        for (i, c) in ciphertext.into_iter().enumerate() {
+            chip.expose_public(layouter.namespace(|| format!("expose c_{i}")), c, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_poseidon_encrypt() {
+    let plaintext = [(); 3].map(|_| Fp::random(OsRng));
+    let key = Fp::random(OsRng);
+
+    let mut sponge = TranscriptSponge::new();
+    sponge.absorb(key);
+    let ciphertext: Vec<_> = plaintext
+        .iter()
+        .map(|p| {
+            let c = *p + sponge.squeeze();
+            sponge.absorb(c);
+            c
+        })
+        .collect();
+
+    let gadget = |key: Fp| EncryptionGadget {
+        plaintext: plaintext.map(Value::known),
+        key: Value::known(key),
+    };
+    assert!(verify_gadget(9, gadget(key), ciphertext.clone()));
+    assert!(!verify_gadget(9, gadget(key), plaintext.to_vec()));
+    assert!(!verify_gadget(9, gadget(key + Fp::ONE), ciphertext));
+}
+
+#[test]
+fn test_arithmetic_circuit() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let d = (a + b) * c;
+    let circuit = ArithmeticCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = ArithmeticCircuit::<Fp>::configure(&mut meta);
+    assert_eq!(meta.num_fixed_columns(), 0);
+    assert_eq!(config.column_stats().selectors, meta.num_selectors());
+
+    // `MyCircuit` hashes `d` as well, which does not fit in 2^5 rows.
+    let k = 5;
+    let prover = MockProver::run(k, &circuit, vec![vec![d]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(k, &circuit, vec![vec![d + Fp::ONE]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let my_circuit = MyCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+    assert!(MockProver::run(k, &my_circuit, vec![my_circuit.expected_public_inputs()]).is_err());
+}
+
+/// Configured like `ArithmeticCircuit`, without the Poseidon sponge, but hashes anyway.
+#[derive(Default)]
+struct ArithmeticHashCircuit {
+    a: Value<Fp>,
+}
+
+impl Circuit<Fp> for ArithmeticHashCircuit {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        ArithmeticCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let digest = chip.poseidon_hash(layouter.namespace(|| "hash a"), [a])?;
+        chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)
+    }
+}
+
+#[test]
+fn test_hash_without_sponge() {
+    let a = Fp::random(OsRng);
+    let circuit = ArithmeticHashCircuit { a: Value::known(a) };
+
+    // The missing sponge is a synthesis error rather than a panic.
+    assert!(matches!(
+        MockProver::run(5, &circuit, vec![vec![poseidon_native([a])]]),
+        Err(Error::Synthesis)
+    ));
+}
+
+#[derive(Default)]
+struct SelfCommitGadget {
+    value: Value<Fp>,
+}
+
+impl Gadget for SelfCommitGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let value = chip.load_private(layouter.namespace(|| "load value"), self.value)?;
+        Ok(chip.self_commit(&mut layouter, value, 0)?)
+    }
+}
+
+#[test]
+fn test_self_commit() {
+    let value = Fp::random(OsRng);
+    let gadget = || SelfCommitGadget {
+        value: Value::known(value),
+    };
+
+    assert!(verify_gadget(7, gadget(), vec![poseidon_native([value])]));
+    assert!(!verify_gadget(7, gadget(), vec![value]));
+}
+
+#[derive(Default)]
+struct RlcGadget {
+    values: [Value<Fp>; 4],
+    challenge: Value<Fp>,
+}
+
+impl Gadget for RlcGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = chip.load_private_iter(
+            layouter.namespace(|| "load values"),
+            self.values.iter().copied(),
+        )?;
+        let challenge =
+            chip.load_private(layouter.namespace(|| "load challenge"), self.challenge)?;
+
+        let rlc = chip.random_linear_combination(&mut layouter, &values, challenge)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose rlc"), rlc, 0)?)
+    }
+}
+
+#[test]
+fn test_random_linear_combination() {
+    let values = [(); 4].map(|_| Fp::random(OsRng));
+    let challenge = Fp::random(OsRng);
+    let gadget = || RlcGadget {
+        values: values.map(Value::known),
+        challenge: Value::known(challenge),
+    };
+
+    let rlc = values[0]
+        + values[1] * challenge
+        + values[2] * challenge.square()
+        + values[3] * challenge.square() * challenge;
+    assert!(verify_gadget(6, gadget(), vec![rlc]));
+    assert!(!verify_gadget(6, gadget(), vec![values.iter().sum()]));
+}
+
+#[derive(Default)]
+struct ExternalChallengeGadget {
+    values: [Value<Fp>; 4],
+    challenge: Value<Fp>,
+}
+
+impl Gadget for ExternalChallengeGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = chip.load_private_iter(
+            layouter.namespace(|| "load values"),
+            self.values.iter().copied(),
+        )?;
+        let challenge =
+            chip.load_challenge(layouter.namespace(|| "load challenge"), self.challenge, 0)?;
+
+        let rlc = chip.random_linear_combination(&mut layouter, &values, challenge)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose rlc"), rlc, 1)?)
+    }
+}
+
+#[test]
+fn test_load_challenge() {
+    let values = [(); 4].map(|_| Fp::random(OsRng));
+    let challenge = Fp::random(OsRng);
+    let gadget = |challenge: Fp| ExternalChallengeGadget {
+        values: values.map(Value::known),
+        challenge: Value::known(challenge),
+    };
+
+    let rlc = |challenge: Fp| {
+        values
+            .iter()
+            .rev()
+            .fold(Fp::ZERO, |acc, value| acc * challenge + value)
+    };
+    assert!(verify_gadget(
+        6,
+        gadget(challenge),
+        vec![challenge, rlc(challenge)]
+    ));
+
+    // The prover cannot substitute a challenge of its own choosing.
+    let other = challenge + Fp::ONE;
+    assert!(!verify_gadget(
+        6,
+        gadget(other),
+        vec![challenge, rlc(other)]
+    ));
+}
+
+#[derive(Default)]
+struct HashInSetGadget {
+    candidates: [Value<Fp>; 3],
+}
+
+impl Gadget for HashInSetGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let candidates = chip.load_private_iter(
+            layouter.namespace(|| "load candidates"),
+            self.candidates.iter().copied(),
+        )?;
+        let digest = chip.load_instance(layouter.namespace(|| "load digest"), 0)?;
+
+        chip.assert_hash_in_set(&mut layouter, digest, &candidates)
+    }
+}
+
+#[test]
+fn test_assert_hash_in_set() {
+    let candidates = [(); 3].map(|_| Fp::random(OsRng));
+    let gadget = || HashInSetGadget {
+        candidates: candidates.map(Value::known),
+    };
+
+    for candidate in candidates {
+        assert!(verify_gadget(
+            8,
+            gadget(),
+            vec![poseidon_native([candidate])]
+        ));
+    }
+    assert!(!verify_gadget(8, gadget(), vec![candidates[0]]));
+}
+
+#[derive(Default)]
+struct XorChecksumGadget {
+    bytes: [Value<Fp>; 4],
+}
+
+impl Gadget for XorChecksumGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let bytes = chip.load_private_iter(
+            layouter.namespace(|| "load bytes"),
+            self.bytes.iter().copied(),
+        )?;
+
+        let checksum = chip.xor_checksum(&mut layouter, &bytes, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose checksum"), checksum, 0)?)
+    }
+}
+
+#[test]
+fn test_xor_checksum() {
+    let gadget = |bytes: [u64; 4]| XorChecksumGadget {
+        bytes: bytes.map(|byte| Value::known(Fp::from(byte))),
+    };
+
+    // 0x12 ^ 0x34 ^ 0x56 ^ 0x78 = 0x08
+    let bytes = [0x12, 0x34, 0x56, 0x78];
+    assert!(verify_gadget(10, gadget(bytes), vec![Fp::from(0x08)]));
+    assert!(!verify_gadget(
+        10,
+        gadget(bytes),
+        vec![Fp::from(0x12 + 0x34 + 0x56 + 0x78)]
+    ));
+
+    // A value wider than a byte fails its decomposition.
+    assert!(!verify_gadget(
+        10,
+        gadget([0x112, 0x34, 0x56, 0x78]),
+        vec![Fp::from(0x08)]
+    ));
+}
+
+#[derive(Default)]
+struct InRangeGadget {
+    a: Value<Fp>,
+    lo: Fp,
+    hi: Fp,
+}
+
+impl Gadget for InRangeGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        chip.assert_in_range(&mut layouter, a, self.lo, self.hi, 8)
+    }
+}
+
+#[test]
+fn test_assert_in_range() {
+    let gadget = |a: u64, lo: u64, hi: u64| InRangeGadget {
+        a: Value::known(Fp::from(a)),
+        lo: Fp::from(lo),
+        hi: Fp::from(hi),
+    };
+
+    for a in [10, 11, 50, 99, 100] {
+        assert!(verify_gadget(8, gadget(a, 10, 100), vec![]));
+    }
+    for a in [0, 9, 101, 255, 1000, -1i64 as u64] {
+        assert!(matches!(
+            gadget_error(8, gadget(a, 10, 100)),
+            PlaygroundError::OutOfRange { n_bits: 8 }
+        ));
+    }
+    assert!(verify_gadget(8, gadget(42, 42, 42), vec![]));
+
+    assert!(matches!(
+        gadget_error(8, gadget(50, 100, 10)),
+        PlaygroundError::EmptyRange
+    ));
+}
+
+#[derive(Default)]
+struct DivModGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for DivModGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let (q, r) = chip.divmod(&mut layouter, a, b, 8)?;
+        chip.expose_public(layouter.namespace(|| "expose q"), q, 0)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose r"), r, 1)?)
+    }
+}
+
+#[test]
+fn test_divmod() {
+    let gadget = |a: u64, b: u64| DivModGadget {
+        a: Value::known(Fp::from(a)),
+        b: Value::known(Fp::from(b)),
+    };
+
+    assert!(verify_gadget(
+        8,
+        gadget(17, 5),
+        vec![Fp::from(3), Fp::from(2)]
+    ));
+    assert!(verify_gadget(8, gadget(15, 5), vec![Fp::from(3), Fp::ZERO]));
+    assert!(verify_gadget(8, gadget(4, 5), vec![Fp::ZERO, Fp::from(4)]));
+    assert!(!verify_gadget(
+        8,
+        gadget(17, 5),
+        vec![Fp::from(2), Fp::from(7)]
+    ));
+
+    // The field quotient of 17 / 5 is not the integer one.
+    let field_quotient = Fp::from(17) * Fp::from(5).invert().unwrap();
+    assert!(!verify_gadget(
+        8,
+        gadget(17, 5),
+        vec![field_quotient, Fp::ZERO]
+    ));
+
+    assert!(matches!(
+        gadget_error(8, gadget(17, 0)),
+        PlaygroundError::DivisionByZero
+    ));
+}
+
+#[derive(Default)]
+struct AssertDivModGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    q: Value<Fp>,
+    r: Value<Fp>,
+}
+
+impl Gadget for AssertDivModGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let [a, b, q, r] = [self.a, self.b, self.q, self.r];
+        let a = chip.load_private(layouter.namespace(|| "load a"), a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), b)?;
+        let q = chip.load_private(layouter.namespace(|| "load q"), q)?;
+        let r = chip.load_private(layouter.namespace(|| "load r"), r)?;
+
+        chip.assert_divmod(&mut layouter, a, b, q, r, 8)
+    }
+}
+
+#[test]
+fn test_assert_divmod() {
+    let gadget = |a: u64, b: u64, q: Fp, r: Fp| AssertDivModGadget {
+        a: Value::known(Fp::from(a)),
+        b: Value::known(Fp::from(b)),
+        q: Value::known(q),
+        r: Value::known(r),
+    };
+
+    assert!(verify_gadget(
+        8,
+        gadget(17, 5, Fp::from(3), Fp::from(2)),
+        vec![]
+    ));
+
+    // 4 * 5 - 3 = 17 in the field, but the remainder is negative.
+    assert!(!verify_gadget(
+        8,
+        gadget(17, 5, Fp::from(4), -Fp::from(3)),
+        vec![]
+    ));
+
+    // 0 * 261 + 17 = 17 with 17 < 261, but the divisor does not fit in 8 bits.
+    assert!(!verify_gadget(
+        8,
+        gadget(17, 261, Fp::ZERO, Fp::from(17)),
+        vec![]
+    ));
+}
+
+#[derive(Default)]
+struct BitReverseGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for BitReverseGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+
+        let reversed = chip.bit_reverse(&mut layouter, a, 4)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose reversed"), reversed, 0)?)
+    }
+}
+
+#[test]
+fn test_bit_reverse() {
+    let gadget = |a: u64| BitReverseGadget {
+        a: Value::known(Fp::from(a)),
+    };
+
+    assert!(verify_gadget(7, gadget(0b0001), vec![Fp::from(0b1000)]));
+    assert!(verify_gadget(7, gadget(0b0110), vec![Fp::from(0b0110)]));
+    assert!(verify_gadget(7, gadget(0b1011), vec![Fp::from(0b1101)]));
+    assert!(!verify_gadget(7, gadget(0b0001), vec![Fp::from(0b0001)]));
+}
+
+#[test]
+fn test_verify_strategies() {
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::random(OsRng),
+        c: Fp::random(OsRng),
+    };
+    let circuit = CommittedCircuit::from(inputs);
+    let public_inputs = circuit.expected_public_inputs();
+
+    let k = 7;
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let vk = keygen_vk(&params, &circuit).unwrap();
+    let pk = keygen_pk(&params, vk, &circuit).unwrap();
+    let proof = create_real_proof(&params, &pk, circuit, &public_inputs).unwrap();
+
+    let mut wrong_inputs = public_inputs.clone();
+    wrong_inputs[0] += Fp::ONE;
+    for strategy in [VerifyStrategy::Single, VerifyStrategy::Accumulator] {
+        assert!(verify_real_proof_with(
+            &params,
+            pk.get_vk(),
+            &proof,
+            &public_inputs,
+            strategy
+        ));
+        assert!(!verify_real_proof_with(
+            &params,
+            pk.get_vk(),
+            &proof,
+            &wrong_inputs,
+            strategy
+        ));
+    }
+}
+
+#[test]
+fn test_verify_batch() {
+    let k = 7;
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let circuits = (0..3)
+        .map(|_| {
+            CommittedCircuit::from(CircuitInputs {
+                a: Fp::random(OsRng),
+                b: Fp::random(OsRng),
+                c: Fp::random(OsRng),
+            })
+        })
+        .collect::<Vec<_>>();
+    let vk = keygen_vk(&params, &circuits[0]).unwrap();
+    let pk = keygen_pk(&params, vk, &circuits[0]).unwrap();
+
+    let public_inputs = circuits
+        .iter()
+        .map(|circuit| circuit.expected_public_inputs())
+        .collect::<Vec<_>>();
+    let proofs = circuits
+        .into_iter()
+        .zip(&public_inputs)
+        .map(|(circuit, public_inputs)| {
+            create_real_proof(&params, &pk, circuit, public_inputs).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let mut wrong_inputs = public_inputs[1].clone();
+    wrong_inputs[0] += Fp::ONE;
+    let verify = |public_inputs: &[&[Fp]], strategy| {
+        let instances = public_inputs
+            .iter()
+            .map(|inputs| [*inputs])
+            .collect::<Vec<_>>();
+        let batch = proofs
+            .iter()
+            .zip(&instances)
+            .map(|(proof, instances)| (&proof[..], &instances[..]))
+            .collect::<Vec<_>>();
+        verify_proofs(&params, pk.get_vk(), &batch, strategy)
+    };
+
+    for strategy in [VerifyStrategy::Single, VerifyStrategy::Accumulator] {
+        let good = public_inputs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert!(verify(&good, strategy).is_ok());
+
+        // A single bad proof fails the whole batch, even when the others are fine.
+        let mut bad = good.clone();
+        bad[1] = &wrong_inputs[..];
+        assert!(verify(&bad, strategy).is_err());
+
+        // Swapping the instances of two proofs breaks both of them.
+        let mut swapped = good.clone();
+        swapped.swap(0, 2);
+        assert!(verify(&swapped, strategy).is_err());
+    }
+}
+
+/// The number of low digest bits `CollisionCircuit` compares, short enough for a collision
+/// to be found by brute force.
+const COLLISION_BITS: usize = 8;
+
+/// Proves knowledge of two distinct preimages whose Poseidon digests agree on their low
+/// `COLLISION_BITS` bits.
+#[derive(Default)]
+struct CollisionCircuit {
+    x: Value<Fp>,
+    y: Value<Fp>,
+}
+
+impl Circuit<Fp> for CollisionCircuit {
+    type Config = FieldConfig<Fp, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyCircuit::<Fp>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FieldChip::<Fp, WIDTH, RATE>::construct(config, ());
+
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.x)?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.y)?;
+        chip.assert_all_distinct(&mut layouter, &[x.clone(), y.clone()])?;
+
+        let x_bits = chip.hash_to_bits(&mut layouter, [x], COLLISION_BITS)?;
+        let y_bits = chip.hash_to_bits(&mut layouter, [y], COLLISION_BITS)?;
+        for (i, (x_bit, y_bit)) in x_bits.into_iter().zip(y_bits).enumerate() {
+            chip.assert_equal(
+                layouter.namespace(|| format!("bit_{i} matches")),
+                x_bit,
+                y_bit,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_collision_circuit() {
+    let truncate = |x: Fp| {
+        let digest = poseidon_native([x]).to_repr();
+        u16::from_le_bytes([digest[0], digest[1]]) & ((1 << COLLISION_BITS) - 1)
+    };
+
+    // By the pigeonhole principle, a collision turns up within 2^COLLISION_BITS + 1 tries.
+    let mut seen = std::collections::HashMap::new();
+    let (x, y) = (0..=1u64 << COLLISION_BITS)
+        .map(Fp::from)
+        .find_map(|y| seen.insert(truncate(y), y).map(|x| (x, y)))
+        .unwrap();
+    assert_ne!(poseidon_native([x]), poseidon_native([y]));
+
+    let circuit = |x: Fp, y: Fp| CollisionCircuit {
+        x: Value::known(x),
+        y: Value::known(y),
+    };
+    let prover = MockProver::run(12, &circuit(x, y), vec![vec![]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The same preimage twice is not a collision.
+    let prover = MockProver::run(12, &circuit(x, x), vec![vec![]]);
+    assert!(prover.map_or(true, |prover| prover.verify().is_err()));
+
+    // Nor are two preimages whose digests differ in the compared bits.
+    let z = (0..)
+        .map(Fp::from)
+        .find(|z| truncate(*z) != truncate(x))
+        .unwrap();
+    let prover = MockProver::run(12, &circuit(x, z), vec![vec![]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[derive(Default)]
+struct WeightedSumGadget {
+    values: [Value<Fp>; 4],
+    coeffs: Vec<Fp>,
+}
+
+impl Gadget for WeightedSumGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = chip.load_private_iter(
+            layouter.namespace(|| "load values"),
+            self.values.iter().copied(),
+        )?;
+
+        let sum = chip.weighted_sum_public(&mut layouter, &values, &self.coeffs)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose sum"), sum, 0)?)
+    }
+}
+
+#[test]
+fn test_weighted_sum_public() {
+    let values = [(); 4].map(|_| Fp::random(OsRng));
+    let coeffs = [3, 1, 4, 1].map(Fp::from);
+    let gadget = |coeffs: &[Fp]| WeightedSumGadget {
+        values: values.map(Value::known),
+        coeffs: coeffs.to_vec(),
+    };
+
+    let expected = values.iter().zip(&coeffs).map(|(v, c)| *v * c).sum();
+    assert!(verify_gadget(6, gadget(&coeffs), vec![expected]));
+    assert!(!verify_gadget(
+        6,
+        gadget(&coeffs),
+        vec![values.iter().sum()]
+    ));
+
+    // A missing coefficient is a length mismatch rather than a shorter sum.
+    assert!(matches!(
+        gadget_error(6, gadget(&coeffs[..3])),
+        PlaygroundError::LengthMismatch {
+            expected: 3,
+            actual: 4
+        }
+    ));
+}
+
+#[derive(Default)]
+struct PermutationGadget {
+    input: [Value<Fp>; 4],
+    output: [Value<Fp>; 4],
+    challenge: Value<Fp>,
+}
+
+impl Gadget for PermutationGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let input = chip.load_private_iter(
+            layouter.namespace(|| "load input"),
+            self.input.iter().copied(),
+        )?;
+        let output = chip.load_private_iter(
+            layouter.namespace(|| "load output"),
+            self.output.iter().copied(),
+        )?;
+        let challenge =
+            chip.load_private(layouter.namespace(|| "load challenge"), self.challenge)?;
+
+        chip.assert_permutation(&mut layouter, &input, &output, challenge)
+    }
+}
+
+#[test]
+fn test_assert_permutation() {
+    let input = [1, 2, 3, 4].map(Fp::from);
+    let challenge = Fp::random(OsRng);
+    let gadget = |output: [Fp; 4]| PermutationGadget {
+        input: input.map(Value::known),
+        output: output.map(Value::known),
+        challenge: Value::known(challenge),
+    };
+
+    assert!(verify_gadget(7, gadget([3, 1, 4, 2].map(Fp::from)), vec![]));
+    assert!(verify_gadget(7, gadget(input), vec![]));
+
+    // Same sum, but not a permutation.
+    assert!(!verify_gadget(
+        7,
+        gadget([1, 1, 4, 4].map(Fp::from)),
+        vec![]
+    ));
+}
+
+/// An IV standing in for one taken from another Poseidon instance.
+struct TestIv;
+
+impl CapacityIv for TestIv {
+    fn capacity() -> Fp {
+        Fp::from(0xdead_beef)
+    }
+}
+
+#[derive(Default)]
+struct CapacityIvGadget {
+    message: Value<Fp>,
+}
+
+impl Gadget for CapacityIvGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let message = chip.load_private(layouter.namespace(|| "load message"), self.message)?;
+
+        let mut sponge =
+            new_sponge_with_capacity::<TestIv>(chip, layouter.namespace(|| "new sponge"))?;
+        let padding = <WithCapacity<TestIv, L> as Domain<Fp, RATE>>::padding(L);
+        for (i, word) in [PaddedWord::Message(message.into())]
+            .into_iter()
+            .chain(padding.map(PaddedWord::Padding))
+            .enumerate()
+        {
+            sponge.absorb(layouter.namespace(|| format!("absorb_{i}")), word)?;
+        }
+        let mut sponge = sponge.finish_absorbing(layouter.namespace(|| "finish absorbing"))?;
+        let digest = sponge.squeeze(layouter.namespace(|| "squeeze"))?;
+
+        Ok(chip.expose_public(layouter.namespace(|| "expose digest"), digest.into(), 0)?)
+    }
+}
+
+#[test]
+fn test_sponge_capacity_iv() {
+    let message = Fp::random(OsRng);
+    let gadget = || CapacityIvGadget {
+        message: Value::known(message),
+    };
+
+    let digest = poseidon::Hash::<_, P128Pow5T3, WithCapacity<TestIv, L>, WIDTH, RATE>::init()
+        .hash([message]);
+    assert_ne!(digest, poseidon_native([message]));
+
+    // The native hash starts from the same state as a transcript sponge with that capacity.
+    let mut sponge = TranscriptSponge::with_capacity(TestIv::capacity());
+    sponge.absorb(message);
+    for word in <WithCapacity<TestIv, L> as Domain<Fp, RATE>>::padding(L) {
+        sponge.absorb(word);
+    }
+    assert_eq!(sponge.squeeze(), digest);
+
+    assert!(verify_gadget(7, gadget(), vec![digest]));
+    assert!(!verify_gadget(
+        7,
+        gadget(),
+        vec![poseidon_native([message])]
+    ));
+}
+
+#[derive(Default)]
+struct SchnorrScalarGadget {
+    s: Value<Fp>,
+    e: Value<Fp>,
+    x: Value<Fp>,
+    r: Value<Fp>,
+}
+
+impl Gadget for SchnorrScalarGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let s = chip.load_private(layouter.namespace(|| "load s"), self.s)?;
+        let e = chip.load_private(layouter.namespace(|| "load e"), self.e)?;
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.x)?;
+        let r = chip.load_private(layouter.namespace(|| "load r"), self.r)?;
+
+        Ok(chip.verify_schnorr_scalar(&mut layouter, s, e, x, r)?)
+    }
+}
+
+#[test]
+fn test_verify_schnorr_scalar() {
+    let [e, x, r] = [(); 3].map(|_| Fp::random(OsRng));
+    let gadget = |s: Fp| SchnorrScalarGadget {
+        s: Value::known(s),
+        e: Value::known(e),
+        x: Value::known(x),
+        r: Value::known(r),
+    };
+
+    assert!(verify_gadget(5, gadget(r + e * x), vec![]));
+    assert!(!verify_gadget(5, gadget(r - e * x), vec![]));
+}
+
+#[test]
+fn test_public_layout() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let circuit = MyCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+    let public_inputs = circuit.expected_public_inputs();
+
+    let layout = MyCircuit::<Fp>::public_layout();
+    let rows: Vec<_> = layout.iter().map(|(row, _)| *row).collect();
+    assert_eq!(rows, (0..public_inputs.len()).collect::<Vec<_>>());
+
+    let d = (a + b) * c;
+    for (row, name) in layout {
+        let expected = match name {
+            "d" => d,
+            "digest" => poseidon_native([d]),
+            _ => panic!("unexpected public input {name}"),
+        };
+        assert_eq!(public_inputs[row], expected, "row {row} should hold {name}");
+    }
+
+    let prover = MockProver::run(7, &circuit, vec![public_inputs]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[derive(Default)]
+struct MaskedSumGadget {
+    values: [Value<Fp>; 5],
+    mask: [Value<Fp>; 5],
+}
+
+impl Gadget for MaskedSumGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = chip.load_private_iter(
+            layouter.namespace(|| "load values"),
+            self.values.iter().copied(),
+        )?;
+        let mask = chip.load_private_iter(
+            layouter.namespace(|| "load mask"),
+            self.mask.iter().copied(),
+        )?;
+
+        let sum = chip.masked_sum(&mut layouter, &values, &mask)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose sum"), sum, 0)?)
+    }
+}
+
+#[test]
+fn test_masked_sum() {
+    let values = [(); 5].map(|_| Fp::random(OsRng));
+    let gadget = |mask: [u64; 5]| MaskedSumGadget {
+        values: values.map(Value::known),
+        mask: mask.map(|bit| Value::known(Fp::from(bit))),
+    };
+
+    let expected = values[0] + values[2] + values[3];
+    assert!(verify_gadget(7, gadget([1, 0, 1, 1, 0]), vec![expected]));
+    assert!(!verify_gadget(
+        7,
+        gadget([1, 0, 1, 1, 0]),
+        vec![values.iter().sum()]
+    ));
+
+    // A non-boolean mask cannot scale a value instead of selecting it.
+    assert!(!verify_gadget(
+        7,
+        gadget([1, 0, 1, 1, 2]),
+        vec![expected + values[4].double()]
+    ));
+}
+
+#[test]
+fn test_circuit_fuzz() {
+    // A fixed seed keeps any failure reproducible.
+    let mut rng = ChaCha20Rng::seed_from_u64(0x706c_6179);
+
+    for _ in 0..32 {
+        let [a, b, c] = [(); 3].map(|_| Fp::random(&mut rng));
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(c),
+        };
+        let public_inputs = circuit.expected_public_inputs();
+
+        let prover = MockProver::run(7, &circuit, vec![public_inputs]).unwrap();
+        assert_eq!(
+            prover.verify(),
+            Ok(()),
+            "circuit not satisfied for a = {a:?}, b = {b:?}, c = {c:?}"
+        );
+    }
+}
+
+#[derive(Default)]
+struct FingerprintGadget {
+    tuples: [[Value<Fp>; 2]; 3],
+    challenge: Value<Fp>,
+}
+
+impl Gadget for FingerprintGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let tuples = self
+            .tuples
+            .iter()
+            .enumerate()
+            .map(|(i, tuple)| {
+                chip.load_private_iter(
+                    layouter.namespace(|| format!("load tuple_{i}")),
+                    tuple.iter().copied(),
+                )
+                .map(|cells| [cells[0].clone(), cells[1].clone()])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let challenge =
+            chip.load_private(layouter.namespace(|| "load challenge"), self.challenge)?;
+
+        let fingerprint = chip.fingerprint(&mut layouter, &tuples, challenge)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose fingerprint"), fingerprint, 0)?)
+    }
+}
+
+#[test]
+fn test_fingerprint() {
+    let tuples = [[0, 7], [1, 8], [2, 9]].map(|tuple| tuple.map(Fp::from));
+    let challenge = Fp::random(OsRng);
+    let gadget = |tuples: [[Fp; 2]; 3]| FingerprintGadget {
+        tuples: tuples.map(|tuple| tuple.map(Value::known)),
+        challenge: Value::known(challenge),
+    };
+
+    let fingerprint = |tuples: &[[Fp; 2]]| {
+        tuples
+            .iter()
+            .map(|[addr, val]| *addr + challenge * val + challenge.square())
+            .product::<Fp>()
+    };
+    assert!(verify_gadget(7, gadget(tuples), vec![fingerprint(&tuples)]));
+
+    // Swapping the values of two addresses changes the fingerprint.
+    let mut swapped = tuples;
+    swapped[0][1] = tuples[1][1];
+    swapped[1][1] = tuples[0][1];
+    assert!(!verify_gadget(
+        7,
+        gadget(swapped),
+        vec![fingerprint(&tuples)]
+    ));
+    assert!(verify_gadget(
+        7,
+        gadget(swapped),
+        vec![fingerprint(&swapped)]
+    ));
+}
+
+#[test]
+fn test_circuit_builder() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+
+    let mut builder = CircuitBuilder::new();
+    let [a_index, b_index, c_index] = [a, b, c].map(|x| builder.input(Value::known(x)));
+    let sum = builder.push(Op::Add(a_index, b_index)).unwrap();
+    let d = builder.push(Op::Mul(sum, c_index)).unwrap();
+    let digest = builder.push(Op::Hash(d)).unwrap();
+    builder.expose(d).unwrap();
+    builder.expose(digest).unwrap();
+
+    // Operands must come before the operation reading them.
+    assert!(matches!(
+        builder.push(Op::Sub(d, 7)),
+        Err(PlaygroundError::IndexOutOfBounds { index: 7, len: 6 })
+    ));
+
+    let my_circuit = MyCircuit {
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+    let public_inputs = my_circuit.expected_public_inputs();
+
+    assert!(assert_mock_and_real_agree(builder, 7, &public_inputs));
+}
+
+#[derive(Default)]
+struct HashArrayGadget {
+    arr: Vec<Value<Fp>>,
+}
+
+impl Gadget for HashArrayGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let arr = chip.load_private_iter(
+            layouter.namespace(|| "load array"),
+            self.arr.iter().copied(),
+        )?;
+
+        let digest = chip.hash_array(&mut layouter, &arr)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)?)
+    }
+}
+
+/// Reference digest of `hash_array`.
+fn hash_array_native(arr: &[Fp]) -> Fp {
+    let mut sponge = TranscriptSponge::new();
+    sponge.absorb(Fp::from(arr.len() as u64));
+    for x in arr {
+        sponge.absorb(*x);
+    }
+    sponge.squeeze()
+}
+
+#[test]
+fn test_hash_array() {
+    let short = [Fp::ONE];
+    let long = [Fp::ONE, Fp::ZERO];
+    assert_ne!(hash_array_native(&short), hash_array_native(&long));
+
+    // Without the length prefix, the trailing zero is absorbed without a trace.
+    let unprefixed = |arr: &[Fp]| {
+        let mut sponge = TranscriptSponge::new();
+        for x in arr {
+            sponge.absorb(*x);
+        }
+        sponge.squeeze()
+    };
+    assert_eq!(unprefixed(&short), unprefixed(&long));
+
+    let gadget = |arr: &[Fp]| HashArrayGadget {
+        arr: arr.iter().copied().map(Value::known).collect(),
+    };
+    assert!(verify_gadget(
+        7,
+        gadget(&short),
+        vec![hash_array_native(&short)]
+    ));
+    assert!(verify_gadget(
+        7,
+        gadget(&long),
+        vec![hash_array_native(&long)]
+    ));
+    assert!(!verify_gadget(
+        7,
+        gadget(&long),
+        vec![hash_array_native(&short)]
+    ));
+}
+
+#[test]
+fn test_describe_constraints() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    MyCircuit::<Fp>::configure(&mut meta);
+
+    let report = describe_constraints(&meta);
+
+    let gate = |name: &str| {
+        report
+            .lines()
+            .find(|line| line.starts_with(&format!("gate \"{name}\":")))
+            .unwrap_or_else(|| panic!("gate {name} missing from the report"))
+            .to_string()
+    };
+    assert!(gate("add").contains("degree 2, 1 constraints, columns advice[0], advice[1]"));
+    assert!(gate("mul").contains("degree 3, 1 constraints, columns advice[0], advice[1]"));
+    assert_eq!(report.lines().count(), meta.gates().len() + 1);
+}
+
+#[derive(Default)]
+struct ToBytesGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for ToBytesGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+
+        let bytes = chip.to_bytes(&mut layouter, a)?;
+        for (i, byte) in bytes.into_iter().enumerate() {
+            chip.expose_public(layouter.namespace(|| format!("expose byte_{i}")), byte, i)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_to_bytes() {
+    let a = Fp::random(OsRng);
+    let gadget = || ToBytesGadget { a: Value::known(a) };
+
+    let bytes: Vec<_> = a
+        .to_repr()
+        .iter()
+        .map(|byte| Fp::from(*byte as u64))
+        .collect();
+    let recomposed = bytes
+        .iter()
+        .rev()
+        .fold(Fp::ZERO, |acc, byte| acc * Fp::from(256) + byte);
+    assert_eq!(recomposed, a);
+
+    assert!(verify_gadget(13, gadget(), bytes.clone()));
+
+    let mut reversed = bytes;
+    reversed.reverse();
+    assert!(!verify_gadget(13, gadget(), reversed));
+}
+
+#[derive(Default)]
+struct AssertBytesGadget {
+    a: Value<Fp>,
+    bytes: Vec<Value<Fp>>,
+}
+
+impl Gadget for AssertBytesGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let bytes = chip.load_private_iter(
+            layouter.namespace(|| "load bytes"),
+            self.bytes.iter().copied(),
+        )?;
+        chip.assert_bytes(&mut layouter, a, &bytes)
+    }
+}
+
+#[test]
+fn test_to_bytes_canonical() {
+    // Draw `a` small enough for `a + 2p` to fit in 256 bits, and so `a + p` in 255.
+    let (a, plus_p, plus_2p) = std::iter::repeat_with(|| Fp::random(OsRng))
+        .find_map(|a| {
+            let plus_p = repr_plus_modulus(a)?;
+            Some((a, plus_p, add_modulus(plus_p)?))
+        })
+        .unwrap();
+    let gadget = |repr: [u8; 32]| AssertBytesGadget {
+        a: Value::known(a),
+        bytes: repr
+            .iter()
+            .map(|byte| Value::known(Fp::from(*byte as u64)))
+            .collect(),
+    };
+    assert!(verify_gadget(13, gadget(a.to_repr()), vec![]));
+
+    // The bytes of `a + p` recompose to `a` in the field, but exceed the modulus, and those
+    // of `a + 2p` also overflow the top byte.
+    assert!(!verify_gadget(13, gadget(plus_p), vec![]));
+    assert_eq!(plus_2p[31] >> 7, 1);
+    assert!(!verify_gadget(13, gadget(plus_2p), vec![]));
+}
+
+#[derive(Default)]
+struct TransitionGadget {
+    input: Value<Fp>,
+}
+
+impl Gadget for TransitionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let old_state = chip.load_instance(layouter.namespace(|| "load old state"), 0)?;
+        let new_state = chip.load_instance(layouter.namespace(|| "load new state"), 1)?;
+        let input = chip.load_private(layouter.namespace(|| "load input"), self.input)?;
+
+        Ok(chip.verify_transition(&mut layouter, old_state, input, new_state)?)
+    }
+}
+
+#[test]
+fn test_verify_transition() {
+    let [old_state, input] = [(); 2].map(|_| Fp::random(OsRng));
+    let new_state = poseidon_native([old_state, input]);
+    let gadget = || TransitionGadget {
+        input: Value::known(input),
+    };
+
+    assert!(verify_gadget(7, gadget(), vec![old_state, new_state]));
+    assert!(!verify_gadget(
+        7,
+        gadget(),
+        vec![old_state, new_state + Fp::ONE]
+    ));
+    assert!(!verify_gadget(7, gadget(), vec![new_state, old_state]));
+}
+
+#[derive(Default)]
+struct MulBoundedGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for MulBoundedGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let product = chip.mul_bounded(&mut layouter, a, b, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose product"), product, 0)?)
+    }
+}
+
+#[test]
+fn test_mul_bounded() {
+    let gadget = |a: Fp, b: Fp| MulBoundedGadget {
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+    let check =
+        |a: u64, b: u64| verify_gadget(9, gadget(Fp::from(a), Fp::from(b)), vec![Fp::from(a * b)]);
+
+    assert!(check(255, 255));
+    assert!(check(0, 255));
+    assert!(check(1, 1));
+    assert!(!verify_gadget(
+        9,
+        gadget(Fp::from(3), Fp::from(5)),
+        vec![Fp::from(16)]
+    ));
+
+    // A wide or negative operand, which would wrap the product around the field, is refused
+    // at synthesis.
+    let minus_one = -Fp::ONE;
+    for (a, b) in [
+        (Fp::from(256), Fp::ONE),
+        (Fp::ONE, Fp::from(256)),
+        (minus_one, minus_one),
+    ] {
+        assert!(matches!(
+            gadget_error(9, gadget(a, b)),
+            PlaygroundError::OutOfRange { n_bits: 8 }
+        ));
+    }
+}
+
+#[derive(Default)]
+struct AddressGadget {
+    pubkey: [Value<Fp>; 2],
+}
+
+impl Gadget for AddressGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.pubkey[0])?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.pubkey[1])?;
+
+        let address = chip.derive_address(&mut layouter, x, y)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose address"), address, 0)?)
+    }
+}
+
+#[test]
+fn test_derive_address() {
+    let pubkey = [(); 2].map(|_| Fp::random(OsRng));
+    let gadget = || AddressGadget {
+        pubkey: pubkey.map(Value::known),
+    };
+
+    let mut repr = poseidon_native(pubkey).to_repr();
+    for byte in &mut repr[ADDRESS_BITS / 8..] {
+        *byte = 0;
+    }
+    let address = Fp::from_repr(repr).unwrap();
+
+    assert!(verify_gadget(12, gadget(), vec![address]));
+    assert!(!verify_gadget(12, gadget(), vec![poseidon_native(pubkey)]));
+}
+
+/// Derives an address like `derive_address`, but from the decomposition `bits` of the
+/// digest given by the test rather than the one the chip witnesses.
+#[derive(Default)]
+struct AddressDecompositionGadget {
+    pubkey: [Value<Fp>; 2],
+    bits: Vec<Value<Fp>>,
+}
+
+impl Gadget for AddressDecompositionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.pubkey[0])?;
+        let y = chip.load_private(layouter.namespace(|| "load y"), self.pubkey[1])?;
+        let bits = chip.load_private_iter(
+            layouter.namespace(|| "load bits"),
+            self.bits.iter().copied(),
+        )?;
+
+        let digest = chip.poseidon_hash(layouter.namespace(|| "hash pubkey"), [x, y])?;
+        chip.assert_bits(&mut layouter, digest, &bits)?;
+        let address = chip.from_bits(&mut layouter, &bits[..ADDRESS_BITS])?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose address"), address, 0)?)
+    }
+}
+
+#[test]
+fn test_derive_address_canonical() {
+    let (pubkey, bits, forged) = digest_with_two_decompositions();
+    let address = |bits: &[Fp]| {
+        bits[..ADDRESS_BITS]
+            .iter()
+            .rev()
+            .fold(Fp::ZERO, |acc, bit| acc.double() + bit)
+    };
+    let gadget = |bits: &[Fp]| AddressDecompositionGadget {
+        pubkey: pubkey.map(Value::known),
+        bits: bits.iter().copied().map(Value::known).collect(),
+    };
+
+    // The low bits of `d + p` would give the pubkey a second address.
+    assert_ne!(address(&bits), address(&forged));
+    assert!(verify_gadget(12, gadget(&bits), vec![address(&bits)]));
+    assert!(!verify_gadget(12, gadget(&forged), vec![address(&forged)]));
+}
+
+#[test]
+fn test_verify_from_bytes() {
+    let inputs = CircuitInputs {
+        a: Fp::random(OsRng),
+        b: Fp::random(OsRng),
+        c: Fp::random(OsRng),
+    };
+    let circuit = CommittedCircuit::from(inputs);
+    let public_inputs = circuit.expected_public_inputs();
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(7);
+    let vk = keygen_vk(&params, &circuit).unwrap();
+    let pk = keygen_pk(&params, vk, &circuit).unwrap();
+    let proof = create_real_proof(&params, &pk, circuit, &public_inputs).unwrap();
+
+    let mut vk_bytes = vec![];
+    pk.get_vk()
+        .write(&mut vk_bytes, SerdeFormat::Processed)
+        .unwrap();
+    let instances = vec![public_inputs];
+    let instances_bytes = encode_instances(&instances);
+    assert_eq!(decode_instances(&instances_bytes).unwrap(), instances);
+
+    let verify = |instances_bytes: &[u8]| {
+        verify_from_bytes::<CommittedCircuit<Fp>>(
+            &vk_bytes,
+            &proof,
+            instances_bytes,
+            VerifyStrategy::default(),
+        )
+    };
+    assert!(verify(&instances_bytes).is_ok());
+
+    let mut wrong_instances = instances.clone();
+    wrong_instances[0][0] += Fp::ONE;
+    assert!(verify(&encode_instances(&wrong_instances)).is_err());
+
+    // Truncated or padded encodings are rejected before verifying anything.
+    let truncated = &instances_bytes[..instances_bytes.len() - 1];
+    assert!(matches!(verify(truncated), Err(Error::Transcript(_))));
+    let mut padded = instances_bytes.clone();
+    padded.push(0);
+    assert!(matches!(verify(&padded), Err(Error::Transcript(_))));
+}
+
+#[derive(Default)]
+struct ConditionalHashGadget {
+    cond: Value<Fp>,
+    a: Value<Fp>,
+    b: Value<Fp>,
+}
+
+impl Gadget for ConditionalHashGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let cond = chip.load_private(layouter.namespace(|| "load cond"), self.cond)?;
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+
+        let digest = chip.conditional_hash(&mut layouter, cond, a, b)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)?)
+    }
+}
+
+#[test]
+fn test_conditional_hash() {
+    let [a, b] = [(); 2].map(|_| Fp::random(OsRng));
+    let gadget = |cond: Fp| ConditionalHashGadget {
+        cond: Value::known(cond),
+        a: Value::known(a),
+        b: Value::known(b),
+    };
+
+    assert!(verify_gadget(
+        7,
+        gadget(Fp::ONE),
+        vec![poseidon_native([a])]
+    ));
+    assert!(verify_gadget(
+        7,
+        gadget(Fp::ZERO),
+        vec![poseidon_native([b])]
+    ));
+    assert!(!verify_gadget(
+        7,
+        gadget(Fp::ONE),
+        vec![poseidon_native([b])]
+    ));
+
+    // A non-boolean condition would hash a blend of the inputs.
+    let blend = b + Fp::from(2) * (a - b);
+    assert!(!verify_gadget(
+        7,
+        gadget(Fp::from(2)),
+        vec![poseidon_native([blend])]
+    ));
+}
+
+#[derive(Default)]
+struct ValidIndexGadget {
+    idx: Value<Fp>,
+    len: usize,
+}
+
+impl Gadget for ValidIndexGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let idx = chip.load_private(layouter.namespace(|| "load idx"), self.idx)?;
+        chip.assert_valid_index(&mut layouter, idx, self.len, 4)
+    }
+}
+
+#[test]
+fn test_assert_valid_index() {
+    let gadget = |idx: u64, len: usize| ValidIndexGadget {
+        idx: Value::known(Fp::from(idx)),
+        len,
+    };
+
+    for idx in [0, 5, 9] {
+        assert!(verify_gadget(7, gadget(idx, 10), vec![]));
+    }
+    for idx in [10, 15] {
+        assert!(!verify_gadget(7, gadget(idx, 10), vec![]));
+    }
+    assert!(!verify_gadget(7, gadget(0, 0), vec![]));
+    assert!(verify_gadget(7, gadget(15, 16), vec![]));
+
+    // 16 does not fit in 4 bits, and neither does the length 17.
+    assert!(matches!(
+        gadget_error(7, gadget(16, 10)),
+        PlaygroundError::OutOfRange { n_bits: 4 }
+    ));
+    assert!(matches!(
+        gadget_error(7, gadget(0, 17)),
+        PlaygroundError::OutOfRange { n_bits: 4 }
+    ));
+}
+
+#[derive(Default)]
+struct SelectIndexGadget {
+    arr: [Value<Fp>; 5],
+    idx: Value<Fp>,
+}
+
+impl Gadget for SelectIndexGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let arr = self
+            .arr
+            .iter()
+            .enumerate()
+            .map(|(i, x)| chip.load_private(layouter.namespace(|| format!("load arr_{i}")), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let idx = chip.load_private(layouter.namespace(|| "load idx"), self.idx)?;
+
+        let selected = chip.select_index(&mut layouter, &arr, idx)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose selected"), selected, 0)?)
+    }
+}
+
+#[test]
+fn test_select_index() {
+    let arr = [(); 5].map(|_| Fp::random(OsRng));
+    let gadget = |idx: u64| SelectIndexGadget {
+        arr: arr.map(Value::known),
+        idx: Value::known(Fp::from(idx)),
+    };
+
+    assert!(verify_gadget(8, gadget(2), vec![arr[2]]));
+    assert!(!verify_gadget(8, gadget(2), vec![arr[3]]));
+    assert!(verify_gadget(8, gadget(4), vec![arr[4]]));
+
+    // Indices past the end are rejected, even where the bits could address them.
+    assert!(!verify_gadget(8, gadget(5), vec![Fp::ZERO]));
+    assert!(!verify_gadget(8, gadget(7), vec![Fp::ZERO]));
+}
+
+#[derive(Default)]
+struct ArraySetGadget {
+    arr: [Value<Fp>; 4],
+    idx: Value<Fp>,
+    value: Value<Fp>,
+}
+
+impl Gadget for ArraySetGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let arr = self
+            .arr
+            .iter()
+            .enumerate()
+            .map(|(i, x)| chip.load_private(layouter.namespace(|| format!("load arr_{i}")), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let idx = chip.load_private(layouter.namespace(|| "load idx"), self.idx)?;
+        let value = chip.load_private(layouter.namespace(|| "load value"), self.value)?;
+
+        let updated = chip.array_set(&mut layouter, &arr, idx, value)?;
+        for (row, x) in updated.into_iter().enumerate() {
+            chip.expose_public(layouter.namespace(|| format!("expose arr_{row}")), x, row)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_array_set() {
+    let arr = [(); 4].map(|_| Fp::random(OsRng));
+    let value = Fp::random(OsRng);
+    let gadget = |idx: u64| ArraySetGadget {
+        arr: arr.map(Value::known),
+        idx: Value::known(Fp::from(idx)),
+        value: Value::known(value),
+    };
+
+    let mut updated = arr.to_vec();
+    updated[2] = value;
+    assert!(verify_gadget(8, gadget(2), updated.clone()));
+    assert!(!verify_gadget(8, gadget(2), arr.to_vec()));
+    assert!(!verify_gadget(8, gadget(1), updated.clone()));
+
+    assert!(matches!(
+        gadget_error(8, gadget(4)),
+        PlaygroundError::OutOfRange { n_bits: 2 }
+    ));
+}
+
+#[derive(Default)]
+struct ComputeAndHashGadget {
+    a: Value<Fp>,
+    b: Value<Fp>,
+    c: Value<Fp>,
+    fused: bool,
+}
+
+impl Gadget for ComputeAndHashGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let c = chip.load_private(layouter.namespace(|| "load c"), self.c)?;
+
+        let (d, digest) = if self.fused {
+            chip.compute_and_hash(&mut layouter, a, b, c)?
+        } else {
+            let d = chip.add_and_mul(&mut layouter, a, b, c)?;
+            let digest = chip.poseidon_hash(layouter.namespace(|| "hash d"), [d.clone()])?;
+            (d, digest)
+        };
+        chip.expose_public(layouter.namespace(|| "expose d"), d, 0)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose digest"), digest, 1)?)
+    }
+}
+
+#[test]
+fn test_compute_and_hash() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let d = (a + b) * c;
+    let digest = poseidon_native([d]);
+
+    let rows = |fused, public_inputs| {
+        let circuit = GadgetCircuit::new(ComputeAndHashGadget {
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(c),
+            fused,
+        });
+        let prover = MockProver::run(7, &circuit, vec![public_inputs]).unwrap();
+        (prover.verify().is_ok(), used_rows(&prover))
+    };
+
+    let (unfused_ok, unfused_rows) = rows(false, vec![d, digest]);
+    let (fused_ok, fused_rows) = rows(true, vec![d, digest]);
+    assert!(unfused_ok && fused_ok);
+    assert!(fused_rows < unfused_rows);
+
+    assert!(!rows(true, vec![d + Fp::ONE, digest]).0);
+    assert!(!rows(true, vec![d, poseidon_native([d + Fp::ONE])]).0);
+}
+
+#[derive(Default)]
+struct PrefixConsistentGadget {
+    digest_short: Value<Fp>,
+    digest_long: Value<Fp>,
+    prefix: [Value<Fp>; 2],
+    suffix: [Value<Fp>; 1],
+}
+
+impl Gadget for PrefixConsistentGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let digest_short = chip.load_private(
+            layouter.namespace(|| "load digest_short"),
+            self.digest_short,
+        )?;
+        let digest_long =
+            chip.load_private(layouter.namespace(|| "load digest_long"), self.digest_long)?;
+        let prefix = chip.load_private_iter(
+            layouter.namespace(|| "load prefix"),
+            self.prefix.into_iter(),
+        )?;
+        let suffix = chip.load_private_iter(
+            layouter.namespace(|| "load suffix"),
+            self.suffix.into_iter(),
+        )?;
+
+        Ok(chip.assert_prefix_consistent(
+            &mut layouter,
+            digest_short,
+            digest_long,
+            &prefix,
+            &suffix,
+        )?)
+    }
+}
+
+#[test]
+fn test_assert_prefix_consistent() {
+    let prefix = [(); 2].map(|_| Fp::random(OsRng));
+    let suffix = [Fp::random(OsRng)];
+    let digest_short = hash_array_native(&prefix);
+    let digest_long = hash_array_native(&[&prefix[..], &suffix[..]].concat());
+    let gadget = |digest_short, digest_long, suffix: [Fp; 1]| PrefixConsistentGadget {
+        digest_short: Value::known(digest_short),
+        digest_long: Value::known(digest_long),
+        prefix: prefix.map(Value::known),
+        suffix: suffix.map(Value::known),
+    };
+
+    assert!(verify_gadget(
+        8,
+        gadget(digest_short, digest_long, suffix),
+        vec![]
+    ));
+
+    // Swapped digests, a digest of the wrong data and a different suffix all fail.
+    assert!(!verify_gadget(
+        8,
+        gadget(digest_long, digest_short, suffix),
+        vec![]
+    ));
+    assert!(!verify_gadget(
+        8,
+        gadget(digest_short, hash_array_native(&prefix), suffix),
+        vec![]
+    ));
+    assert!(!verify_gadget(
+        8,
+        gadget(digest_short, digest_long, [suffix[0] + Fp::ONE]),
+        vec![]
+    ));
+}
+
+#[test]
+fn test_render_layout_svg() {
+    // A name unique to this process, so that concurrent test runs do not race on it.
+    let path = std::env::temp_dir().join(format!(
+        "halo2-playground-layout-{}.svg",
+        std::process::id()
+    ));
+    let rendered = render_layout(
+        &MyCircuit::<Fp>::default(),
+        6,
+        "Example Circuit Layout",
+        &path,
+    );
+
+    // Clean up before checking anything, so that a failure does not leave the file behind.
+    let svg = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    rendered.unwrap();
+    assert!(svg.unwrap().contains("<svg"));
+}
+
+#[derive(Default)]
+struct XorConstGadget {
+    a: Value<Fp>,
+    mask: Fp,
+}
+
+impl Gadget for XorConstGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let xored = chip.xor_const(&mut layouter, a, self.mask, 4)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose a ^ mask"), xored, 0)?)
+    }
+}
+
+#[test]
+fn test_xor_const() {
+    let gadget = |a: u64, mask: u64| XorConstGadget {
+        a: Value::known(Fp::from(a)),
+        mask: Fp::from(mask),
+    };
+
+    assert!(verify_gadget(
+        7,
+        gadget(0b1010, 0b0110),
+        vec![Fp::from(0b1100)]
+    ));
+    assert!(!verify_gadget(
+        7,
+        gadget(0b1010, 0b0110),
+        vec![Fp::from(0b1010)]
+    ));
+    assert!(verify_gadget(7, gadget(0b1010, 0), vec![Fp::from(0b1010)]));
+    assert!(verify_gadget(
+        7,
+        gadget(0b1010, 0b1111),
+        vec![Fp::from(0b0101)]
+    ));
+
+    // Neither `a` nor the mask may exceed the 4 bits.
+    assert!(!verify_gadget(
+        7,
+        gadget(0b11010, 0b0110),
+        vec![Fp::from(0b11100)]
+    ));
+    assert!(matches!(
+        gadget_error(7, gadget(0b1010, 0b10110)),
+        PlaygroundError::OutOfRange { n_bits: 4 }
+    ));
+}
+
+/// Synthesizing `MyCircuit` twice from the same inputs must assign the same witness, since
+/// the Fiat-Shamir challenges are derived from it.
+#[test]
+fn test_circuit_deterministic() {
+    let [a, b, c] = [(); 3].map(|_| Fp::random(OsRng));
+    let advice = || {
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(c),
+        };
+        let public_inputs = circuit.expected_public_inputs();
+        let prover = MockProver::run(6, &circuit, vec![public_inputs]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        prover.advice().clone()
+    };
+
+    let first = advice();
+    assert!(first
+        .iter()
+        .flatten()
+        .any(|cell| matches!(cell, CellValue::Assigned(_))));
+    assert_eq!(first, advice());
+}
+
+#[derive(Default)]
+struct KzgOpeningGadget {
+    coeffs: [Value<Fp>; 3],
+    z: Value<Fp>,
+    claimed: Value<Fp>,
+}
+
+impl Gadget for KzgOpeningGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let coeffs = chip.load_private_iter(
+            layouter.namespace(|| "load coeffs"),
+            self.coeffs.into_iter(),
+        )?;
+        let z = chip.load_private(layouter.namespace(|| "load z"), self.z)?;
+        let claimed = chip.load_private(layouter.namespace(|| "load claimed"), self.claimed)?;
+
+        chip.verify_kzg_opening_scalar(&mut layouter, &coeffs, z, claimed)
+    }
+}
+
+#[test]
+fn test_verify_kzg_opening_scalar() {
+    // p(X) = 3 + 2X + X^2, so p(5) = 38.
+    let gadget = |claimed: u64| KzgOpeningGadget {
+        coeffs: [3, 2, 1].map(|c| Value::known(Fp::from(c))),
+        z: Value::known(Fp::from(5)),
+        claimed: Value::known(Fp::from(claimed)),
+    };
+
+    assert!(verify_gadget(5, gadget(38), vec![]));
+    assert!(!verify_gadget(5, gadget(39), vec![]));
+}
+
+#[derive(Default)]
+struct FibonacciGadget {
+    f0: Value<Fp>,
+    f1: Value<Fp>,
+    n: usize,
+}
+
+impl Gadget for FibonacciGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let f0 = chip.load_private(layouter.namespace(|| "load f0"), self.f0)?;
+        let f1 = chip.load_private(layouter.namespace(|| "load f1"), self.f1)?;
+
+        let f_n = chip.fibonacci(layouter.namespace(|| "fibonacci"), f0, f1, self.n)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose f_n"), f_n, 0)?)
+    }
+}
+
+#[test]
+fn test_fibonacci() {
+    let gadget = |n: usize| FibonacciGadget {
+        f0: Value::known(Fp::ONE),
+        f1: Value::known(Fp::ONE),
+        n,
+    };
+
+    assert!(verify_gadget(5, gadget(10), vec![Fp::from(89)]));
+    assert!(!verify_gadget(5, gadget(10), vec![Fp::from(55)]));
+    assert!(verify_gadget(5, gadget(2), vec![Fp::from(2)]));
+    assert!(verify_gadget(5, gadget(1), vec![Fp::ONE]));
+}
+
+#[derive(Default)]
+struct HashSparseGadget {
+    entries: Vec<(usize, Value<Fp>)>,
+    len: usize,
+    sparse: bool,
+}
+
+impl Gadget for HashSparseGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(index, value)| {
+                let value = chip
+                    .load_private(layouter.namespace(|| format!("load entry_{index}")), *value)?;
+                Ok::<_, Error>((*index, value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let digest = if self.sparse {
+            chip.hash_sparse(&mut layouter, &entries, self.len)?
+        } else {
+            let mut dense = vec![Value::known(Fp::ZERO); self.len];
+            for (index, value) in &self.entries {
+                dense[*index] = *value;
+            }
+            let dense =
+                chip.load_private_iter(layouter.namespace(|| "load dense"), dense.into_iter())?;
+            chip.hash_array(&mut layouter, &dense)?
+        };
+        Ok(chip.expose_public(layouter.namespace(|| "expose digest"), digest, 0)?)
+    }
+}
+
+#[test]
+fn test_hash_sparse() {
+    let [x, y] = [(); 2].map(|_| Fp::random(OsRng));
+    let mut dense = vec![Fp::ZERO; 6];
+    dense[1] = x;
+    dense[4] = y;
+    let digest = hash_array_native(&dense);
+
+    let gadget = |entries: &[(usize, Fp)], sparse| HashSparseGadget {
+        entries: entries
+            .iter()
+            .map(|(index, value)| (*index, Value::known(*value)))
+            .collect(),
+        len: 6,
+        sparse,
+    };
+
+    assert!(verify_gadget(
+        8,
+        gadget(&[(1, x), (4, y)], true),
+        vec![digest]
+    ));
+    assert!(verify_gadget(
+        8,
+        gadget(&[(1, x), (4, y)], false),
+        vec![digest]
+    ));
+    assert!(verify_gadget(
+        8,
+        gadget(&[(4, y), (1, x)], true),
+        vec![digest]
+    ));
+    assert!(!verify_gadget(
+        8,
+        gadget(&[(1, x), (3, y)], true),
+        vec![digest]
+    ));
+
+    // Repeated or out-of-bounds indices are rejected.
+    assert!(matches!(
+        gadget_error(8, gadget(&[(1, x), (1, y)], true)),
+        PlaygroundError::DuplicateInput
+    ));
+    assert!(matches!(
+        gadget_error(8, gadget(&[(1, x), (6, y)], true)),
+        PlaygroundError::IndexOutOfBounds { index: 6, len: 6 }
+    ));
+}
+
+#[test]
+fn test_vk_hash() {
+    let k = 7;
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(k);
+    let vk = || keygen_vk(&params, &CommittedCircuit::<Fp>::default()).unwrap();
+
+    assert_eq!(vk_hash(&vk()), vk_hash(&vk()));
+
+    // A different circuit is identified by a different digest.
+    let other = keygen_vk(&params, &MyCircuit::<Fp>::default()).unwrap();
+    assert_ne!(vk_hash(&vk()), vk_hash(&other));
+}
+
+#[derive(Default)]
+struct BoundedAccumulateGadget {
+    values: Vec<Value<Fp>>,
+    max: Fp,
+}
+
+impl Gadget for BoundedAccumulateGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let values = chip.load_private_iter(
+            layouter.namespace(|| "load values"),
+            self.values.iter().copied(),
+        )?;
+        let total = chip.bounded_accumulate(&mut layouter, &values, self.max, 8)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose total"), total, 0)?)
+    }
+}
+
+#[test]
+fn test_bounded_accumulate() {
+    let gadget = |values: &[i64]| BoundedAccumulateGadget {
+        values: values
+            .iter()
+            .map(|v| {
+                let abs = Fp::from(v.unsigned_abs());
+                Value::known(if *v < 0 { -abs } else { abs })
+            })
+            .collect(),
+        max: Fp::from(100),
+    };
+
+    assert!(verify_gadget(9, gadget(&[30, 40, 20]), vec![Fp::from(90)]));
+    assert!(verify_gadget(9, gadget(&[30, 40, 30]), vec![Fp::from(100)]));
+    assert!(!verify_gadget(9, gadget(&[30, 40, 20]), vec![Fp::from(91)]));
+
+    // The budget is exceeded at the last step.
+    assert!(!verify_gadget(
+        9,
+        gadget(&[30, 40, 31]),
+        vec![Fp::from(101)]
+    ));
+
+    // A negative value cannot bring an overrun back under the bound.
+    assert!(matches!(
+        gadget_error(9, gadget(&[90, 20, -10])),
+        PlaygroundError::OutOfRange { n_bits: 8 }
+    ));
+}
+
+#[derive(Default)]
+struct SmtInclusionGadget {
+    key: Value<Fp>,
+    value: Value<Fp>,
+    siblings: [Value<Fp>; 4],
+    root: Value<Fp>,
+}
+
+impl Gadget for SmtInclusionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let key = chip.load_private(layouter.namespace(|| "load key"), self.key)?;
+        let value = chip.load_private(layouter.namespace(|| "load value"), self.value)?;
+        let siblings = chip.load_private_iter(
+            layouter.namespace(|| "load siblings"),
+            self.siblings.into_iter(),
+        )?;
+        let root = chip.load_private(layouter.namespace(|| "load root"), self.root)?;
+
+        chip.verify_smt_inclusion(&mut layouter, key, value, &siblings, root)
+    }
+}
+
+#[test]
+fn test_verify_smt_inclusion() {
+    let key = 0b1011u64;
+    let value = Fp::random(OsRng);
+    let siblings = [(); 4].map(|_| Fp::random(OsRng));
+
+    let root = smt_root_native(key, poseidon_native([Fp::from(key), value]), &siblings);
+
+    let gadget = |key: u64, value: Fp| SmtInclusionGadget {
+        key: Value::known(Fp::from(key)),
+        value: Value::known(value),
+        siblings: siblings.map(Value::known),
+        root: Value::known(root),
+    };
+
+    assert!(verify_gadget(9, gadget(key, value), vec![]));
+    assert!(!verify_gadget(9, gadget(key, value + Fp::ONE), vec![]));
+    assert!(!verify_gadget(9, gadget(0b1010, value), vec![]));
+
+    // A key beyond the 16 leaves of the tree cannot be decomposed into its 4 bits.
+    assert!(!verify_gadget(9, gadget(key + 16, value), vec![]));
+}
+
+/// Reference root of a sparse Merkle tree whose leaf at `key` is `leaf`, with `siblings`
+/// from the bottom up.
+fn smt_root_native(key: u64, leaf: Fp, siblings: &[Fp]) -> Fp {
+    siblings
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (i, sibling)| {
+            if key >> i & 1 == 1 {
+                poseidon_native([*sibling, node])
+            } else {
+                poseidon_native([node, *sibling])
+            }
+        })
+}
+
+#[derive(Default)]
+struct SmtExclusionGadget {
+    key: Value<Fp>,
+    siblings: [Value<Fp>; 4],
+    root: Value<Fp>,
+}
+
+impl Gadget for SmtExclusionGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let key = chip.load_private(layouter.namespace(|| "load key"), self.key)?;
+        let siblings = chip.load_private_iter(
+            layouter.namespace(|| "load siblings"),
+            self.siblings.into_iter(),
+        )?;
+        let root = chip.load_private(layouter.namespace(|| "load root"), self.root)?;
+
+        chip.verify_smt_exclusion(&mut layouter, key, &siblings, root)
+    }
+}
+
+#[test]
+fn test_verify_smt_exclusion() {
+    let key = 0b0110u64;
+    let siblings = [(); 4].map(|_| Fp::random(OsRng));
+    let gadget = |root: Fp| SmtExclusionGadget {
+        key: Value::known(Fp::from(key)),
+        siblings: siblings.map(Value::known),
+        root: Value::known(root),
+    };
+
+    let empty_root = smt_root_native(key, Fp::ZERO, &siblings);
+    assert!(verify_gadget(9, gadget(empty_root), vec![]));
+
+    // Once a value is stored at the key, it is no longer absent.
+    let leaf = poseidon_native([Fp::from(key), Fp::random(OsRng)]);
+    assert!(!verify_gadget(
+        9,
+        gadget(smt_root_native(key, leaf, &siblings)),
+        vec![]
+    ));
+}
+
+#[derive(Default)]
+struct RatchetGadget {
+    seed: Value<Fp>,
+    steps: usize,
+}
+
+impl Gadget for RatchetGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let mut challenge = chip.load_private(layouter.namespace(|| "load seed"), self.seed)?;
+        let mut transcript = Transcript::new(chip, layouter.namespace(|| "init transcript"))?;
+        for step in 0..self.steps {
+            challenge = chip.ratchet_challenge(
+                &mut layouter.namespace(|| format!("step_{step}")),
+                &mut transcript,
+                challenge,
+            )?;
+            chip.expose_public(
+                layouter.namespace(|| format!("expose challenge_{step}")),
+                challenge.clone(),
+                step,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ratchet_challenge() {
+    let seed = Fp::random(OsRng);
+    let mut sponge = TranscriptSponge::new();
+    let chain = (0..3)
+        .scan(seed, |prev, _| {
+            sponge.absorb(*prev);
+            *prev = sponge.squeeze();
+            Some(*prev)
+        })
+        .collect::<Vec<_>>();
+    assert!(chain[0] != chain[1] && chain[1] != chain[2]);
+
+    // Restarting the sponge at each step gives a different chain from the second step on.
+    let restarted = {
+        let mut sponge = TranscriptSponge::new();
+        sponge.absorb(chain[0]);
+        sponge.squeeze()
+    };
+    assert_ne!(restarted, chain[1]);
+
+    let gadget = || RatchetGadget {
+        seed: Value::known(seed),
+        steps: 3,
+    };
+    assert!(verify_gadget(8, gadget(), chain.clone()));
+    assert!(!verify_gadget(
+        8,
+        gadget(),
+        vec![chain[0], restarted, chain[2]]
+    ));
+
+    let mut reordered = chain;
+    reordered.swap(1, 2);
+    assert!(!verify_gadget(8, gadget(), reordered));
+}
+
+#[derive(Default)]
+struct PopcountGadget {
+    a: Value<Fp>,
+}
+
+impl Gadget for PopcountGadget {
+    fn synthesize(
+        &self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlaygroundError> {
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let popcount = chip.popcount(&mut layouter, a, 5)?;
+        Ok(chip.expose_public(layouter.namespace(|| "expose popcount"), popcount, 0)?)
+    }
+}
+
+#[test]
+fn test_popcount() {
+    let gadget = |a: u64| PopcountGadget {
+        a: Value::known(Fp::from(a)),
+    };
+
+    assert!(verify_gadget(6, gadget(0b10110), vec![Fp::from(3)]));
+    assert!(!verify_gadget(6, gadget(0b10110), vec![Fp::from(2)]));
+    assert!(verify_gadget(6, gadget(0), vec![Fp::ZERO]));
+    assert!(verify_gadget(6, gadget(0b11111), vec![Fp::from(5)]));
+
+    // A value wider than 5 bits fails its decomposition.
+    assert!(!verify_gadget(6, gadget(0b110110), vec![Fp::from(4)]));
+}
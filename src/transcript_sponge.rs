@@ -0,0 +1,311 @@
+//! A duplex Poseidon sponge for Fiat-Shamir transcripts, both natively and in-circuit.
+//!
+//! The `Sponge` from `halo2_gadgets` can only go from absorbing to squeezing once, so a
+//! transcript that interleaves absorbs and squeezes over several rounds of a protocol
+//! cannot be built on it. The sponges here can switch back and forth: squeezing permutes
+//! any pending inputs into the state first, and absorbing after a squeeze starts a new
+//! rate buffer.
+//!
+//! Inputs are not padded, so the transcript relies on the protocol fixing how many
+//! elements are absorbed between squeezes.
+
+use halo2_gadgets::poseidon::{
+    primitives::{P128Pow5T3, Spec},
+    PoseidonInstructions, Pow5Chip, StateWord,
+};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter},
+    plonk::Error,
+};
+use halo2curves::pasta::Fp;
+
+use crate::{FieldChip, Number, RATE, WIDTH};
+
+/// The rate portion of a sponge, either being filled with inputs or drained of outputs.
+#[derive(Clone, Debug)]
+enum Mode<T> {
+    Absorbing([Option<T>; RATE]),
+    Squeezing([Option<T>; RATE]),
+}
+
+impl<T> Mode<T> {
+    /// Returns a fresh absorbing buffer holding only `value`.
+    fn init_with(value: T) -> Self {
+        let mut buffer = [(); RATE].map(|_| None);
+        buffer[0] = Some(value);
+        Mode::Absorbing(buffer)
+    }
+
+    /// Returns a squeezing buffer holding the rate portion of `state`.
+    fn outputs(state: &[T; WIDTH]) -> Self
+    where
+        T: Clone,
+    {
+        let mut buffer = [(); RATE].map(|_| None);
+        for (output, word) in buffer.iter_mut().zip(state.iter()) {
+            *output = Some(word.clone());
+        }
+        Mode::Squeezing(buffer)
+    }
+}
+
+/// The Poseidon permutation of `P128Pow5T3`, as computed by `Pow5Chip`.
+fn permute(state: &mut [Fp; WIDTH]) {
+    let (round_constants, mds, _) = <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::constants();
+    let r_f = <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::full_rounds() / 2;
+    let r_p = <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::partial_rounds();
+    let sbox = <P128Pow5T3 as Spec<Fp, WIDTH, RATE>>::sbox;
+
+    for (round, rcs) in round_constants.iter().enumerate() {
+        for (word, rc) in state.iter_mut().zip(rcs.iter()) {
+            *word += rc;
+        }
+
+        // Partial rounds only apply the S-box to the first word of the state.
+        if round < r_f || round >= r_f + r_p {
+            for word in state.iter_mut() {
+                *word = sbox(*word);
+            }
+        } else {
+            state[0] = sbox(state[0]);
+        }
+
+        let mut mixed = [Fp::ZERO; WIDTH];
+        for (mixed, row) in mixed.iter_mut().zip(mds.iter()) {
+            *mixed = row
+                .iter()
+                .zip(state.iter())
+                .map(|(m, word)| *m * word)
+                .sum();
+        }
+        *state = mixed;
+    }
+}
+
+/// A native duplex sponge, used to compute the challenges an in-circuit `CircuitTranscript`
+/// fed the same inputs will squeeze.
+#[derive(Clone, Debug)]
+pub struct TranscriptSponge {
+    state: [Fp; WIDTH],
+    mode: Mode<Fp>,
+}
+
+impl Default for TranscriptSponge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranscriptSponge {
+    /// Returns a sponge with an all-zero initial state.
+    pub fn new() -> Self {
+        Self::with_capacity(Fp::ZERO)
+    }
+
+    /// Returns a sponge whose capacity word starts out as `capacity` instead of zero.
+    ///
+    /// With the initial capacity element of a `Domain` and its padding absorbed after the
+    /// message, this reproduces the native Poseidon sponge of `halo2_gadgets`.
+    pub fn with_capacity(capacity: Fp) -> Self {
+        let mut state = [Fp::ZERO; WIDTH];
+        state[RATE] = capacity;
+
+        Self {
+            state,
+            mode: Mode::Absorbing([None; RATE]),
+        }
+    }
+
+    /// Absorbs `value` into the transcript.
+    pub fn absorb(&mut self, value: Fp) {
+        if let Mode::Absorbing(buffer) = &mut self.mode {
+            if let Some(entry) = buffer.iter_mut().find(|entry| entry.is_none()) {
+                *entry = Some(value);
+                return;
+            }
+
+            // The rate buffer is full, so it has to be permuted into the state first.
+            self.permute_inputs();
+        }
+
+        self.mode = Mode::init_with(value);
+    }
+
+    /// Squeezes a challenge out of the transcript.
+    pub fn squeeze(&mut self) -> Fp {
+        loop {
+            if let Mode::Squeezing(buffer) = &mut self.mode {
+                if let Some(output) = buffer.iter_mut().find_map(Option::take) {
+                    return output;
+                }
+            }
+
+            // Either inputs are pending or the outputs are exhausted.
+            self.permute_inputs();
+        }
+    }
+
+    /// Adds any pending inputs to the state, permutes it and makes its rate portion
+    /// available for squeezing.
+    fn permute_inputs(&mut self) {
+        if let Mode::Absorbing(buffer) = &self.mode {
+            for (word, input) in self.state.iter_mut().zip(buffer.iter()) {
+                if let Some(input) = input {
+                    *word += input;
+                }
+            }
+        }
+
+        permute(&mut self.state);
+        self.mode = Mode::outputs(&self.state);
+    }
+}
+
+/// The in-circuit counterpart of `TranscriptSponge`, permuting with the `FieldChip`'s
+/// `Pow5Chip`.
+#[derive(Clone, Debug)]
+pub struct CircuitTranscript {
+    state: [Number<Fp>; WIDTH],
+    mode: Mode<Number<Fp>>,
+}
+
+impl CircuitTranscript {
+    /// Returns a transcript with an all-zero initial state, fixed by the constants column.
+    pub fn new(
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<Self, Error> {
+        let mut state = Vec::with_capacity(WIDTH);
+        for i in 0..WIDTH {
+            state.push(chip.load_constant(layouter.namespace(|| format!("state_{i}")), Fp::ZERO)?);
+        }
+
+        Ok(Self {
+            state: state.try_into().unwrap(),
+            mode: Mode::Absorbing([(); RATE].map(|_| None)),
+        })
+    }
+
+    /// Absorbs `value` into the transcript.
+    pub fn absorb(
+        &mut self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+        value: Number<Fp>,
+    ) -> Result<(), Error> {
+        if let Mode::Absorbing(buffer) = &mut self.mode {
+            if let Some(entry) = buffer.iter_mut().find(|entry| entry.is_none()) {
+                *entry = Some(value);
+                return Ok(());
+            }
+
+            // The rate buffer is full, so it has to be permuted into the state first.
+            self.permute_inputs(chip, layouter.namespace(|| "permute"))?;
+        }
+
+        self.mode = Mode::init_with(value);
+
+        Ok(())
+    }
+
+    /// Squeezes a challenge out of the transcript.
+    pub fn squeeze(
+        &mut self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<Number<Fp>, Error> {
+        loop {
+            if let Mode::Squeezing(buffer) = &mut self.mode {
+                if let Some(output) = buffer.iter_mut().find_map(Option::take) {
+                    return Ok(output);
+                }
+            }
+
+            // Either inputs are pending or the outputs are exhausted.
+            self.permute_inputs(chip, layouter.namespace(|| "permute"))?;
+        }
+    }
+
+    /// Adds any pending inputs to the state, permutes it and makes its rate portion
+    /// available for squeezing.
+    fn permute_inputs(
+        &mut self,
+        chip: &FieldChip<Fp, WIDTH, RATE>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        if let Mode::Absorbing(buffer) = &self.mode {
+            for (i, (word, input)) in self.state.iter_mut().zip(buffer.iter()).enumerate() {
+                if let Some(input) = input {
+                    *word = chip.add(
+                        layouter.namespace(|| format!("absorb_{i}")),
+                        word.clone(),
+                        input.clone(),
+                    )?;
+                }
+            }
+        }
+
+        let pow5_chip = Pow5Chip::construct(chip.config.sponge_config()?);
+        let state = self
+            .state
+            .clone()
+            .map(|word| StateWord::from(AssignedCell::from(word)));
+        let state = <Pow5Chip<Fp, WIDTH, RATE> as PoseidonInstructions<
+            Fp,
+            P128Pow5T3,
+            WIDTH,
+            RATE,
+        >>::permute(&pow5_chip, &mut layouter, &state)?;
+
+        self.state = state.map(|word| AssignedCell::from(word).into());
+        self.mode = Mode::outputs(&self.state);
+
+        Ok(())
+    }
+}
+
+/// A Fiat-Shamir transcript over a `FieldChip`, appending proof elements and deriving
+/// challenges from them over any number of rounds within one synthesis.
+pub struct Transcript<'a> {
+    chip: &'a FieldChip<Fp, WIDTH, RATE>,
+    sponge: CircuitTranscript,
+}
+
+impl<'a> Transcript<'a> {
+    /// Returns an empty transcript, matching a fresh native `TranscriptSponge`.
+    pub fn new(
+        chip: &'a FieldChip<Fp, WIDTH, RATE>,
+        layouter: impl Layouter<Fp>,
+    ) -> Result<Self, Error> {
+        let sponge = CircuitTranscript::new(chip, layouter)?;
+        Ok(Self { chip, sponge })
+    }
+
+    /// Appends `scalar` to the transcript.
+    pub fn append_scalar(
+        &mut self,
+        layouter: impl Layouter<Fp>,
+        scalar: Number<Fp>,
+    ) -> Result<(), Error> {
+        self.sponge.absorb(self.chip, layouter, scalar)
+    }
+
+    /// Appends each of `scalars` to the transcript, in order.
+    pub fn append_scalars(
+        &mut self,
+        layouter: &mut impl Layouter<Fp>,
+        scalars: &[Number<Fp>],
+    ) -> Result<(), Error> {
+        for (i, scalar) in scalars.iter().enumerate() {
+            self.append_scalar(layouter.namespace(|| format!("append_{i}")), scalar.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a challenge derived from everything appended so far.
+    pub fn challenge(&mut self, layouter: impl Layouter<Fp>) -> Result<Number<Fp>, Error> {
+        self.sponge.squeeze(self.chip, layouter)
+    }
+}
@@ -0,0 +1,194 @@
+//! Verification of IPA proofs from their serialized parts, as a standalone verifier would
+//! receive them.
+//!
+//! The verifying key uses halo2's own `SerdeFormat::Processed` encoding. The IPA parameters
+//! are transparent, so they are regenerated from the `k` stored in the key rather than
+//! shipped alongside it. Instances are encoded by `encode_instances`.
+//!
+//! A `VerifyStrategy` picks whether the final IPA opening of each proof is checked right
+//! away or folded into an accumulator shared by a whole batch of proofs.
+//!
+//! `vk_hash` condenses a verifying key into a single field element, so that circuits can
+//! be registered and referred to by it.
+
+use std::io;
+
+use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength, P128Pow5T3};
+use halo2_proofs::{
+    plonk::{verify_proof, Circuit, Error, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::VerifierIPA,
+            strategy::{AccumulatorStrategy, SingleStrategy},
+        },
+        VerificationStrategy,
+    },
+    transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer},
+    SerdeFormat,
+};
+use halo2curves::{
+    ff::PrimeField,
+    pasta::{EqAffine, Fp},
+};
+
+use crate::{RATE, WIDTH};
+
+/// Number of bytes of the verifying key packed into each field element hashed by
+/// `vk_hash`, few enough for any chunk to be a canonical element.
+const VK_CHUNK_BYTES: usize = 31;
+
+/// How the final IPA opening of a proof is checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerifyStrategy {
+    /// Checks the opening of each proof right away.
+    #[default]
+    Single,
+    /// Folds the openings into an accumulator that is only checked when finalized, so that
+    /// a batch of proofs can share the expensive multiscalar multiplication.
+    Accumulator,
+}
+
+/// Encodes the instance columns of a proof: the number of columns, then for each column
+/// its number of rows followed by the rows themselves. Counts are little-endian `u32`s and
+/// field elements their 32-byte little-endian representation.
+pub fn encode_instances(instances: &[Vec<Fp>]) -> Vec<u8> {
+    let mut bytes = (instances.len() as u32).to_le_bytes().to_vec();
+    for column in instances {
+        bytes.extend((column.len() as u32).to_le_bytes());
+        for value in column {
+            bytes.extend(value.to_repr());
+        }
+    }
+
+    bytes
+}
+
+/// Decodes instance columns encoded by `encode_instances`, rejecting truncated input,
+/// trailing bytes and non-canonical field elements.
+pub fn decode_instances(mut bytes: &[u8]) -> io::Result<Vec<Vec<Fp>>> {
+    let n_columns = read_u32(&mut bytes)?;
+    let instances = (0..n_columns)
+        .map(|_| {
+            let n_rows = read_u32(&mut bytes)?;
+            (0..n_rows)
+                .map(|_| read_fp(&mut bytes))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    if !bytes.is_empty() {
+        return Err(invalid_data("trailing bytes after the instances"));
+    }
+
+    Ok(instances)
+}
+
+/// Verifies the proof `proof_bytes` of a circuit of type `C` against the verifying key
+/// `vk_bytes` and the instances `instances_bytes`, using `strategy`.
+///
+/// Malformed input is reported as `Error::Transcript`, an invalid proof as
+/// `Error::ConstraintSystemFailure`.
+pub fn verify_from_bytes<C: Circuit<Fp>>(
+    vk_bytes: &[u8],
+    proof_bytes: &[u8],
+    instances_bytes: &[u8],
+    strategy: VerifyStrategy,
+) -> Result<(), Error> {
+    let vk = VerifyingKey::<EqAffine>::read::<_, C>(&mut &vk_bytes[..], SerdeFormat::Processed)?;
+    let instances = decode_instances(instances_bytes)?;
+    let instances = instances.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(vk.get_domain().k());
+    verify_proofs(&params, &vk, &[(proof_bytes, &instances[..])], strategy)
+}
+
+/// Verifies each of `proofs`, given as a proof and its instance columns, against `vk`.
+///
+/// With `VerifyStrategy::Accumulator` all the openings go into a single accumulator, so a
+/// bad proof is only noticed once the whole batch is finalized, and fails all of it.
+pub fn verify_proofs(
+    params: &ParamsIPA<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    proofs: &[(&[u8], &[&[Fp]])],
+    strategy: VerifyStrategy,
+) -> Result<(), Error> {
+    match strategy {
+        VerifyStrategy::Single => proofs.iter().try_for_each(|(proof, instances)| {
+            verify_one(params, vk, SingleStrategy::new(params), proof, instances)
+        }),
+        VerifyStrategy::Accumulator => {
+            let accumulator = proofs.iter().try_fold(
+                AccumulatorStrategy::new(params),
+                |accumulator, (proof, instances)| {
+                    verify_one(params, vk, accumulator, proof, instances)
+                },
+            )?;
+            if accumulator.finalize() {
+                Ok(())
+            } else {
+                Err(Error::ConstraintSystemFailure)
+            }
+        }
+    }
+}
+
+/// Reads `proof` and checks it against `instances`, handing its opening to `strategy`.
+fn verify_one<'params, S>(
+    params: &'params ParamsIPA<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    strategy: S,
+    proof: &[u8],
+    instances: &[&[Fp]],
+) -> Result<S::Output, Error>
+where
+    S: VerificationStrategy<'params, IPACommitmentScheme<EqAffine>, VerifierIPA<'params, EqAffine>>,
+{
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(proof);
+    verify_proof::<
+        IPACommitmentScheme<EqAffine>,
+        VerifierIPA<EqAffine>,
+        Challenge255<EqAffine>,
+        Blake2bRead<&[u8], EqAffine, Challenge255<EqAffine>>,
+        S,
+    >(params, vk, strategy, &[instances], &mut transcript)
+}
+
+/// Returns a Poseidon digest of `vk`, identifying the circuit it was generated for.
+///
+/// The key is serialized in the `SerdeFormat::Processed` encoding, packed into field
+/// elements of `VK_CHUNK_BYTES` bytes each and chained through 2-to-1 Poseidon hashes
+/// starting from its length in bytes. Keygen is deterministic, so the same circuit always
+/// yields the same digest.
+pub fn vk_hash(vk: &VerifyingKey<EqAffine>) -> Fp {
+    let bytes = vk.to_bytes(SerdeFormat::Processed);
+
+    bytes
+        .chunks(VK_CHUNK_BYTES)
+        .fold(Fp::from(bytes.len() as u64), |acc, chunk| {
+            let mut repr = [0; 32];
+            repr[..chunk.len()].copy_from_slice(chunk);
+            let chunk =
+                Option::from(Fp::from_repr(repr)).expect("31 bytes always fit in a field element");
+
+            poseidon::Hash::<_, P128Pow5T3, ConstantLength<2>, WIDTH, RATE>::init()
+                .hash([acc, chunk])
+        })
+}
+
+fn read_u32(bytes: &mut &[u8]) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    io::Read::read_exact(bytes, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_fp(bytes: &mut &[u8]) -> io::Result<Fp> {
+    let mut repr = [0; 32];
+    io::Read::read_exact(bytes, &mut repr)?;
+    Option::from(Fp::from_repr(repr)).ok_or_else(|| invalid_data("not a canonical field element"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}